@@ -1,6 +1,39 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use jatch::{apply, Patch, Path};
-use serde_json::{json, Value};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use jatch::{apply, apply_mut, diff, get, set, Patch, Path, PathRef};
+use serde_json::{json, Map, Value};
+
+// counts every allocation, so the benchmarks can report how many allocations an operation makes as well as its time
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Print the number of allocations made by a single call to `f`
+fn report_allocations<T>(name: &str, f: impl FnOnce() -> T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(result);
+    println!("{}: {} allocations", name, allocations);
+}
 
 fn deep_json(depth: usize) -> Value {
     if depth == 0 {
@@ -17,8 +50,22 @@ fn deep_json(depth: usize) -> Value {
     }
 }
 
+// an array of objects, each with a wide object inside it
+fn wide_json(width: usize) -> Value {
+    let wide_object = (0..width)
+        .map(|i| (i.to_string(), json!({ "value": i })))
+        .collect::<Map<_, _>>();
+    Value::Array(vec![Value::Object(wide_object); width])
+}
+
 const JSON_DEPTH: usize = 16;
+const JSON_WIDTH: usize = 200;
+// deep enough that comparing every level again at every level of the diff shows up
+const DIFF_DEPTH: usize = 256;
+const DISJOINT_LEN: usize = 2000;
 
+// `apply` and `apply_mut` both patch the document in place, so the copies they patch are made outside the timed code,
+// and the two are timed the same way
 fn deep_insert(c: &mut Criterion) {
     let json = deep_json(JSON_DEPTH);
     let patches = vec![Patch::Add {
@@ -26,7 +73,40 @@ fn deep_insert(c: &mut Criterion) {
         value: json!("value"),
     }];
     c.bench_function("deep_insert", |b| {
-        b.iter(|| apply(json.clone(), patches.clone()))
+        b.iter_batched(
+            || (json.clone(), patches.clone()),
+            |(json, patches)| apply(json, patches),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("deep_insert_mut", |b| {
+        b.iter_batched_ref(
+            || json.clone(),
+            |json| apply_mut(json, &patches),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn wide_insert(c: &mut Criterion) {
+    let json = wide_json(JSON_WIDTH);
+    let patches = vec![Patch::Add {
+        path: Path::new(format!("/{}/{}/new", JSON_WIDTH / 2, JSON_WIDTH / 2)),
+        value: json!("value"),
+    }];
+    c.bench_function("wide_insert", |b| {
+        b.iter_batched(
+            || (json.clone(), patches.clone()),
+            |(json, patches)| apply(json, patches),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("wide_insert_mut", |b| {
+        b.iter_batched_ref(
+            || json.clone(),
+            |json| apply_mut(json, &patches),
+            BatchSize::SmallInput,
+        )
     });
 }
 
@@ -41,9 +121,97 @@ fn append_long_array(c: &mut Criterion) {
     }];
 
     c.bench_function("append_long_array", |b| {
-        b.iter(|| apply(json.clone(), patches.clone()))
+        b.iter_batched(
+            || (json.clone(), patches.clone()),
+            |(json, patches)| apply(json, patches),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("append_long_array_mut", |b| {
+        b.iter_batched_ref(
+            || json.clone(),
+            |json| apply_mut(json, &patches),
+            BatchSize::SmallInput,
+        )
     });
 }
 
-criterion_group!(benches, deep_insert, append_long_array);
+// only the deepest value changes, so every level of the document is on the path to the change
+fn deep_diff(c: &mut Criterion) {
+    for &depth in &[JSON_DEPTH, DIFF_DEPTH] {
+        let before = deep_json(depth);
+        let mut after = before.clone();
+        let deepest = after
+            .pointer_mut(&"/deeper".repeat(depth - 1))
+            .and_then(|value| value.get_mut("number"))
+            .unwrap();
+        *deepest = json!(456);
+        c.bench_function(&format!("deep_diff_{}", depth), |b| {
+            b.iter(|| diff(&before, &after))
+        });
+    }
+}
+
+// no element is shared, so the LCS compares every pair of elements
+fn disjoint_array_diff(c: &mut Criterion) {
+    let before = Value::Array((0..DISJOINT_LEN).map(|i| json!(i)).collect());
+    let after = Value::Array((DISJOINT_LEN..2 * DISJOINT_LEN).map(|i| json!(i)).collect());
+    c.bench_function("disjoint_array_diff", |b| b.iter(|| diff(&before, &after)));
+}
+
+// parsing a pointer and following it, which only allocates with `PathRef` if a token has to be unescaped
+fn deep_pointer(c: &mut Criterion) {
+    let mut json = deep_json(JSON_DEPTH);
+    let pointer = format!("{}/array/2", "/deeper".repeat(JSON_DEPTH - 1));
+
+    let get_with_path = || get(&json, &Path::parse(&pointer).unwrap()).is_ok();
+    let get_with_path_ref = || get(&json, &PathRef::parse(&pointer).unwrap()).is_ok();
+    report_allocations("deep_get_path", get_with_path);
+    report_allocations("deep_get_path_ref", get_with_path_ref);
+    c.bench_function("deep_get_path", |b| b.iter(get_with_path));
+    c.bench_function("deep_get_path_ref", |b| b.iter(get_with_path_ref));
+
+    // patches always own their paths, so they are compared with `set`, which overwrites an object member like the patch does
+    // every run writes the same value, so the document can be reused between runs, and only the member's key is allocated
+    let member = format!("{}/obj/foo", "/deeper".repeat(JSON_DEPTH - 1));
+    let patch =
+        serde_json::to_string(&json!([{"op": "replace", "path": member, "value": 4}])).unwrap();
+    let replace_with_patch = |json: &mut Value| {
+        let patches = serde_json::from_str::<Vec<Patch>>(&patch).unwrap();
+        apply_mut(json, &patches).unwrap();
+    };
+    let set_with_path =
+        |json: &mut Value| set(json, &Path::parse(&member).unwrap(), json!(4)).unwrap();
+    let set_with_path_ref =
+        |json: &mut Value| set(json, &PathRef::parse(&member).unwrap(), json!(4)).unwrap();
+    report_allocations("deep_replace_patch", || replace_with_patch(&mut json));
+    report_allocations("deep_set_path", || set_with_path(&mut json));
+    report_allocations("deep_set_path_ref", || set_with_path_ref(&mut json));
+    c.bench_function("deep_replace_patch", |b| {
+        b.iter(|| replace_with_patch(&mut json))
+    });
+    c.bench_function("deep_set_path", |b| b.iter(|| set_with_path(&mut json)));
+    c.bench_function("deep_set_path_ref", |b| {
+        b.iter(|| set_with_path_ref(&mut json))
+    });
+
+    // a patch's paths borrowed from the input, rather than each token being copied out of it
+    let patch = serde_json::to_string(&vec![pointer.as_str(); 64]).unwrap();
+    let deserialize_paths = || serde_json::from_str::<Vec<Path>>(&patch).unwrap();
+    let deserialize_path_refs = || serde_json::from_str::<Vec<PathRef>>(&patch).unwrap();
+    report_allocations("deserialize_paths", deserialize_paths);
+    report_allocations("deserialize_path_refs", deserialize_path_refs);
+    c.bench_function("deserialize_paths", |b| b.iter(deserialize_paths));
+    c.bench_function("deserialize_path_refs", |b| b.iter(deserialize_path_refs));
+}
+
+criterion_group!(
+    benches,
+    deep_pointer,
+    deep_insert,
+    wide_insert,
+    append_long_array,
+    deep_diff,
+    disjoint_array_diff
+);
 criterion_main!(benches);
@@ -0,0 +1,205 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Patch, PatchRef, Path};
+
+use super::DiffOptions;
+
+type CostFn = dyn Fn(&PatchRef<'_>) -> usize + Send + Sync;
+
+/// Estimates the cost of a patch, so that [diff_with](crate::diff_with) can choose the cheapest way to express a change
+///
+/// For each changed object or array, the patches that change its contents are compared with a single
+/// [Patch::Replace](crate::Patch::Replace) of the whole value, and the cheaper of the two is used.
+/// No cost model is used unless one is set with [DiffOptions::cost_model](crate::DiffOptions::cost_model).
+///
+/// Patches are costed as [PatchRef]s, so the replacement is only cloned from the document if it is cheaper
+#[derive(Clone)]
+pub enum CostModel {
+    /// The number of bytes in the patch when serialized as JSON
+    Size,
+    /// The value returned from this function
+    Fn(Arc<CostFn>),
+}
+
+impl CostModel {
+    /// Estimate the cost of a patch with `f`
+    pub fn with(f: impl Fn(&PatchRef<'_>) -> usize + Send + Sync + 'static) -> Self {
+        CostModel::Fn(Arc::new(f))
+    }
+
+    /// The cost of `patch`
+    ///
+    /// For [CostModel::Size], this includes the comma or bracket after the patch, so the cost of a list of patches is
+    /// the sum of the costs of its patches
+    fn cost(&self, patch: &Patch) -> usize {
+        match self {
+            CostModel::Size => size_below(patch, usize::MAX).unwrap_or(usize::MAX),
+            CostModel::Fn(f) => f(&patch.to_patch_ref()),
+        }
+    }
+
+    /// The total cost of `patches`, or `None` as soon as it is known to be at least `limit`
+    fn cost_below(&self, patches: &[PatchRef<'_>], limit: usize) -> Option<usize> {
+        patches.iter().try_fold(0, |cost: usize, patch| {
+            let patch_cost = match self {
+                // stopping early means a large value is never serialized in full when it is too expensive
+                CostModel::Size => size_below(patch, limit - cost)?,
+                CostModel::Fn(f) => f(patch),
+            };
+            Some(cost.saturating_add(patch_cost)).filter(|&cost| cost < limit)
+        })
+    }
+}
+
+impl Debug for CostModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CostModel::Size => f.write_str("Size"),
+            CostModel::Fn(_) => f.write_str("Fn(..)"),
+        }
+    }
+}
+
+/// The size of `patch` serialized as JSON, plus one for the comma or bracket after it, or `None` once it is at least `limit`
+fn size_below(patch: &impl Serialize, limit: usize) -> Option<usize> {
+    let mut counter = ByteCounter { count: 1, limit };
+    serde_json::to_writer(&mut counter, patch).ok()?;
+    Some(counter.count).filter(|&count| count < limit)
+}
+
+/// Counts the bytes written to it, so the size of a patch can be measured without allocating
+///
+/// Writing fails once `limit` or more bytes have been written
+struct ByteCounter {
+    count: usize,
+    limit: usize,
+}
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len();
+        if self.count >= self.limit {
+            return Err(io::Error::other("over the limit"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Patches along with their total cost, so the patches for a subtree are costed once, rather than again for each parent
+#[derive(Default)]
+pub(super) struct Costed {
+    pub(super) patches: Vec<Patch>,
+    /// The total cost of `patches`, which is always 0 without a cost model
+    cost: usize,
+}
+
+impl Costed {
+    /// Cost `patches` with the cost model in `options`, if there is one
+    pub(super) fn new(patches: Vec<Patch>, options: &DiffOptions) -> Self {
+        let cost = match &options.cost_model {
+            Some(model) => patches
+                .iter()
+                .map(|patch| model.cost(patch))
+                .fold(0, usize::saturating_add),
+            None => 0,
+        };
+        Self { patches, cost }
+    }
+
+    pub(super) fn append(&mut self, other: Costed) {
+        self.patches.extend(other.patches);
+        self.cost = self.cost.saturating_add(other.cost);
+    }
+}
+
+/// Pick the cheaper of `patches`, which change the contents of the value at `root`, and a replacement of the whole value
+///
+/// Since this runs for every changed container on the way back up, each subtree has already been made as cheap as possible
+pub(super) fn cheapest(
+    before: &Value,
+    after: &Value,
+    root: Path,
+    patches: Costed,
+    options: &DiffOptions,
+) -> Costed {
+    let model = match &options.cost_model {
+        Some(model) => model,
+        None => return patches,
+    };
+    // replacing the whole value would overwrite the ignored values below it
+    if patches.patches.is_empty() || options.contains_ignored(&root) {
+        return patches;
+    }
+
+    // the same patches as `replace`, borrowing the values so they are only cloned if the replacement is cheaper
+    let mut replacement = vec![];
+    if options.test_guards {
+        replacement.push(PatchRef::Test {
+            path: root.clone(),
+            value: before,
+        });
+    }
+    replacement.push(PatchRef::Replace {
+        path: root,
+        value: after,
+    });
+    match model.cost_below(&replacement, patches.cost) {
+        Some(cost) => Costed {
+            patches: replacement.into_iter().map(Patch::from).collect(),
+            cost,
+        },
+        None => patches,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn size_should_match_serialized_length() {
+        let patches = vec![
+            Patch::Add {
+                path: Path::new("/a"),
+                value: json!({"b": [1, 2, 3]}),
+            },
+            Patch::Remove {
+                path: Path::new("/c"),
+            },
+        ];
+        let options = DiffOptions::default().cost_model(CostModel::Size);
+        // every patch is followed by a comma or the closing bracket, and only the opening bracket isn't counted
+        assert_eq!(
+            Costed::new(patches.clone(), &options).cost,
+            serde_json::to_string(&patches).unwrap().len() - 1
+        );
+        let options = DiffOptions::default().cost_model(CostModel::with(|_| 3));
+        assert_eq!(Costed::new(patches, &options).cost, 6);
+    }
+
+    #[test]
+    fn cost_below_should_stop_at_the_limit() {
+        let value = json!({"b": [1, 2, 3]});
+        let patches = vec![PatchRef::Replace {
+            path: Path::new("/a"),
+            value: &value,
+        }];
+        let size = serde_json::to_string(&patches).unwrap().len() - 1;
+        assert_eq!(CostModel::Size.cost_below(&patches, size + 1), Some(size));
+        assert_eq!(CostModel::Size.cost_below(&patches, size), None);
+        assert_eq!(CostModel::Size.cost_below(&patches, 1), None);
+
+        let model = CostModel::with(|_| 3);
+        assert_eq!(model.cost_below(&patches, 4), Some(3));
+        assert_eq!(model.cost_below(&patches, 3), None);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use serde_json::Value;
+
+use crate::{eq::hash_number, json_eq};
+
+/// The structural hashes of every object and array in a set of documents, computed once so they can be reused
+///
+/// Each hash is built from the hashes of its children, like a Merkle tree, so hashing a document is linear in its size.
+/// Values are identified by their address, which is stable since the documents are borrowed for as long as this exists.
+/// Addresses are stored as integers, so that the hashes can be shared between threads
+pub(crate) struct Hashes<'a> {
+    containers: HashMap<usize, u64>,
+    documents: PhantomData<&'a Value>,
+}
+
+impl<'a> Hashes<'a> {
+    pub(crate) fn new(before: &'a Value, after: &'a Value) -> Self {
+        let hash_document = |document| {
+            let mut containers = HashMap::new();
+            hash_tree(document, &mut containers);
+            containers
+        };
+        #[cfg(feature = "rayon")]
+        let (mut containers, after) =
+            rayon::join(|| hash_document(before), || hash_document(after));
+        #[cfg(not(feature = "rayon"))]
+        let (mut containers, after) = (hash_document(before), hash_document(after));
+        containers.extend(after);
+        Self {
+            containers,
+            documents: PhantomData,
+        }
+    }
+
+    /// The hash of `value`, which is only looked up if `value` is a container in one of the documents
+    pub(crate) fn of(&self, value: &Value) -> u64 {
+        match self.containers.get(&address(value)) {
+            Some(hash) => *hash,
+            None => hash_value(value),
+        }
+    }
+
+    /// Whether `a` and `b` are equal according to [json_eq]
+    ///
+    /// Containers with different hashes are never equal, so a full comparison is only needed when they are most likely equal,
+    /// after which they aren't compared again. Anything else is cheaper to compare directly than to hash
+    pub(crate) fn eq(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Array(_), Value::Array(_)) | (Value::Object(_), Value::Object(_)) => {
+                self.of(a) == self.of(b) && json_eq(a, b)
+            }
+            _ => json_eq(a, b),
+        }
+    }
+}
+
+/// A structural hash of `value`, such that values that are equal according to [json_eq] have equal hashes
+///
+/// Object members are combined in an order-independent way, since objects with the same members compare equal regardless of order
+pub(crate) fn hash_value(value: &Value) -> u64 {
+    hash_tree(value, &mut HashMap::new())
+}
+
+/// Hash `value`, recording the hash of every container in it
+fn hash_tree(value: &Value, containers: &mut HashMap<usize, u64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match value {
+        Value::Null => 0u8.hash(&mut hasher),
+        Value::Bool(b) => (1u8, b).hash(&mut hasher),
+        Value::Number(n) => {
+            2u8.hash(&mut hasher);
+            hash_number(n, &mut hasher);
+        }
+        Value::String(s) => (3u8, s).hash(&mut hasher),
+        Value::Array(vec) => {
+            (4u8, vec.len()).hash(&mut hasher);
+            for value in vec {
+                hash_tree(value, containers).hash(&mut hasher);
+            }
+        }
+        Value::Object(map) => {
+            let members = map.iter().fold(0u64, |sum, (key, value)| {
+                let mut hasher = DefaultHasher::new();
+                (key, hash_tree(value, containers)).hash(&mut hasher);
+                sum.wrapping_add(hasher.finish())
+            });
+            (5u8, map.len(), members).hash(&mut hasher);
+        }
+    }
+    let hash = hasher.finish();
+    if let Value::Array(_) | Value::Object(_) = value {
+        containers.insert(address(value), hash);
+    }
+    hash
+}
+
+fn address(value: &Value) -> usize {
+    let pointer: *const Value = value;
+    pointer as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn equal_values_should_have_equal_hashes() {
+        let values = vec![
+            json!(null),
+            json!(false),
+            json!(1),
+            json!(1.5),
+            json!("1"),
+            json!([1]),
+            json!([[1]]),
+            json!({"a": 1, "b": [1, 2]}),
+            json!({"a": [1, 2], "b": 1}),
+        ];
+        for (i, a) in values.iter().enumerate() {
+            assert_eq!(hash_value(a), hash_value(&a.clone()));
+            for b in &values[i + 1..] {
+                assert_ne!(hash_value(a), hash_value(b), "{} and {}", a, b);
+            }
+        }
+
+        let mut reversed = serde_json::Map::new();
+        reversed.insert("b".to_string(), json!(2));
+        reversed.insert("a".to_string(), json!(1));
+        assert_eq!(
+            hash_value(&Value::Object(reversed)),
+            hash_value(&json!({"a": 1, "b": 2}))
+        );
+        assert_eq!(hash_value(&json!([1, 2])), hash_value(&json!([1.0, 2.0])));
+    }
+
+    #[test]
+    fn hashes_should_agree_with_hash_value() {
+        let before = json!({"a": [1, {"b": 2}], "c": {"d": null}});
+        let after = json!({"a": [1, {"b": 3}], "c": {"d": null}});
+        let hashes = Hashes::new(&before, &after);
+        // one for each object and array, in both documents
+        assert_eq!(hashes.containers.len(), 8);
+        for value in [
+            &before,
+            &before["a"],
+            &before["a"][1],
+            &before["c"],
+            &after["a"][1],
+        ]
+        .iter()
+        {
+            assert_eq!(hashes.of(value), hash_value(value));
+        }
+        assert!(hashes.eq(&before["c"], &after["c"]));
+        assert!(!hashes.eq(&before["a"], &after["a"]));
+        assert!(hashes.eq(&before["a"][0], &json!(1.0)));
+    }
+}
@@ -0,0 +1,164 @@
+// a lazy version of `diff`, which produces the same patches in the same order
+// the documents are walked depth first, with a stack of the work that remains, so only the containers on the current
+// path have been compared when a patch is yielded
+// unlike `diff`, the documents aren't hashed up front, which would walk both of them in full before the first patch
+// values are compared with `json_eq` instead, which stops at the first difference, and containers are only compared as members
+// of their parent, so a container that has changed is walked again only down to its first difference
+
+use std::iter::once;
+
+use serde_json::{Map, Value};
+
+use crate::{json_eq, PatchRef, Path};
+
+use super::{lcs, sorted_members};
+
+/// Lazily compute the diff between two JSON Documents
+///
+/// This yields the same patches as [diff](crate::diff), but computes them as they are needed, and borrows values from `after`
+/// rather than cloning them. Patches can be streamed straight to a serializer, or the iterator can be stopped early.
+/// Values are compared as they are reached, so stopping early skips comparing the rest of the documents,
+/// although finding the patches for an array still compares all of its elements:
+/// ```rust
+/// # use serde_json::json;
+/// # use jatch::{diff, diff_iter};
+/// let before = json!({"hello": "world", "list": [1, 2, 3]});
+/// let after = json!({"hello": "there", "list": [1, 3, 4]});
+/// assert!(diff_iter(&before, &after).next().is_some());
+///
+/// let patches = diff_iter(&before, &after).map(|patch| patch.to_patch()).collect::<Vec<_>>();
+/// assert_eq!(patches, diff(&before, &after));
+/// ```
+pub fn diff_iter<'a>(before: &'a Value, after: &'a Value) -> impl Iterator<Item = PatchRef<'a>> {
+    DiffIter {
+        stack: vec![Step::Diff {
+            before,
+            after,
+            path: Path::root(),
+        }],
+    }
+}
+
+enum Step<'a> {
+    /// Diff `before` against `after`, which are both at `path`
+    Diff {
+        before: &'a Value,
+        after: &'a Value,
+        path: Path,
+    },
+    /// Yield a patch that has already been computed
+    Emit(PatchRef<'a>),
+}
+
+struct DiffIter<'a> {
+    /// The remaining work, in reverse order
+    stack: Vec<Step<'a>>,
+}
+
+impl<'a> Iterator for DiffIter<'a> {
+    type Item = PatchRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (before, after, path) = match self.stack.pop()? {
+                Step::Emit(patch) => return Some(patch),
+                Step::Diff {
+                    before,
+                    after,
+                    path,
+                } => (before, after, path),
+            };
+
+            // containers aren't compared as a whole, since comparing their contents finds the same differences
+            let steps = match (before, after) {
+                (Value::Object(before), Value::Object(after)) => diff_maps(before, after, path),
+                (Value::Array(before), Value::Array(after)) => diff_vecs(before, after, path),
+                (before, after) if json_eq(before, after) => continue,
+                (_, value) => return Some(PatchRef::Replace { path, value }),
+            };
+            self.stack.extend(steps.into_iter().rev());
+        }
+    }
+}
+
+fn diff_maps<'a>(
+    before: &'a Map<String, Value>,
+    after: &'a Map<String, Value>,
+    root: Path,
+) -> Vec<Step<'a>> {
+    let before_members = sorted_members(before);
+    let after_members = sorted_members(after);
+
+    let removes = before_members
+        .iter()
+        .filter(|(key, _)| !after.contains_key(*key))
+        .map(|&(key, _)| {
+            Step::Emit(PatchRef::Remove {
+                path: root.child(key),
+            })
+        });
+    let adds = after_members
+        .iter()
+        .filter(|(key, _)| !before.contains_key(*key))
+        .map(|&(key, value)| {
+            Step::Emit(PatchRef::Add {
+                path: root.child(key),
+                value,
+            })
+        });
+    // unchanged members are skipped here, which saves building their paths
+    let changed = before_members.iter().filter_map(|&(key, before)| {
+        let after = after.get(key)?;
+        if json_eq(before, after) {
+            return None;
+        }
+        Some(Step::Diff {
+            before,
+            after,
+            path: root.child(key),
+        })
+    });
+    removes.chain(adds).chain(changed).collect()
+}
+
+// the same as `diff_vecs_by_lcs` with the default options, see there for how the indices are tracked
+fn diff_vecs<'a>(before: &'a [Value], after: &'a [Value], root: Path) -> Vec<Step<'a>> {
+    // without any matches, elements are diffed by index, like `diff_vecs_by_index`
+    let matches = lcs::lcs(before, after, json_eq).unwrap_or_default();
+
+    let mut steps = vec![];
+    let mut index = 0;
+    let (mut i, mut j) = (0, 0);
+
+    for (next_i, next_j) in matches.into_iter().chain(once((before.len(), after.len()))) {
+        let removed = next_i - i;
+        let added = next_j - j;
+        let changed = removed.min(added);
+
+        for offset in 0..changed {
+            steps.push(Step::Diff {
+                before: &before[i + offset],
+                after: &after[j + offset],
+                path: root.child(index.to_string()),
+            });
+            index += 1;
+        }
+        for _ in changed..removed {
+            steps.push(Step::Emit(PatchRef::Remove {
+                path: root.child(index.to_string()),
+            }));
+        }
+        for offset in changed..added {
+            steps.push(Step::Emit(PatchRef::Add {
+                path: root.child(index.to_string()),
+                value: &after[j + offset],
+            }));
+            index += 1;
+        }
+
+        index += 1;
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    steps
+}
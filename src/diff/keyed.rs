@@ -0,0 +1,318 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use serde_json::Value;
+
+use crate::{json_eq, Patch, Path};
+
+use super::{
+    cost::Costed,
+    hash::{hash_value, Hashes},
+    push_remove,
+    steps::Steps,
+    DiffOptions,
+};
+
+type KeyFn = dyn Fn(&Value) -> Option<Value> + Send + Sync;
+
+/// Identifies the elements of an array, so that [diff_with](crate::diff_with) can track them when they move
+///
+/// Elements with the same identity in both arrays are diffed against each other, wherever they are in the array.
+/// Elements without an identity, or whose identity has already been seen earlier in the same array, are never matched
+#[derive(Clone)]
+pub enum ArrayKey {
+    /// Elements are objects, identified by the value of this field
+    Field(String),
+    /// Elements are identified by the value returned from this function
+    Fn(Arc<KeyFn>),
+}
+
+impl ArrayKey {
+    /// Identify elements by the value of `field`
+    pub fn field(field: impl Into<String>) -> Self {
+        ArrayKey::Field(field.into())
+    }
+
+    /// Identify elements by the value returned from `f`
+    pub fn with(f: impl Fn(&Value) -> Option<Value> + Send + Sync + 'static) -> Self {
+        ArrayKey::Fn(Arc::new(f))
+    }
+
+    fn key_of(&self, value: &Value) -> Option<Value> {
+        match self {
+            ArrayKey::Field(field) => value.get(field).cloned(),
+            ArrayKey::Fn(f) => f(value),
+        }
+    }
+}
+
+impl Debug for ArrayKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArrayKey::Field(field) => f.debug_tuple("Field").field(field).finish(),
+            ArrayKey::Fn(_) => f.write_str("Fn(..)"),
+        }
+    }
+}
+
+/// The identity of an element, which is compared with [json_eq] like the rest of the diff, so `1` and `1.0` are the same
+struct Identity(Value);
+
+impl PartialEq for Identity {
+    fn eq(&self, other: &Self) -> bool {
+        json_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Identity {}
+
+impl Hash for Identity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(hash_value(&self.0));
+    }
+}
+
+/// For each element, the index of the element in `after` with the same identity
+fn match_elements(before: &[Value], after: &[Value], key: &ArrayKey) -> Vec<Option<usize>> {
+    let mut after_keys = HashMap::new();
+    for (j, value) in after.iter().enumerate() {
+        if let Some(identity) = key.key_of(value) {
+            after_keys.entry(Identity(identity)).or_insert(j);
+        }
+    }
+
+    let mut matched = vec![None; before.len()];
+    for (i, value) in before.iter().enumerate() {
+        if let Some(identity) = key.key_of(value) {
+            // removing the entry means a duplicate identity in `before` can't match the same element twice
+            matched[i] = after_keys.remove(&Identity(identity));
+        }
+    }
+    matched
+}
+
+// the patches are emitted in 4 phases:
+//  - unmatched elements of `before` are removed
+//  - matched elements are moved into the order they appear in `after`, leaving a longest increasing subsequence in place
+//  - unmatched elements of `after` are added
+//  - matched pairs are diffed, now that every element is at its index in `after`
+pub(super) fn diff_vecs_by_key(
+    before: &[Value],
+    after: &[Value],
+    root: &Path,
+    key: &ArrayKey,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
+    let matched = match_elements(before, after, key);
+    let mut results = Steps::new();
+
+    // removing from the end first means the indices of the remaining elements don't change
+    for i in (0..before.len()).rev().filter(|i| matched[*i].is_none()) {
+        let path = root.child(i.to_string());
+        push_remove(&mut results, path, &before[i], options);
+    }
+
+    // the array now contains the matched elements, in the order of `before`, identified by their index in `after`
+    let current = matched.iter().flatten().copied().collect::<Vec<_>>();
+    let mut target = current.clone();
+    target.sort_unstable();
+
+    let mut stable = vec![false; after.len()];
+    for j in longest_increasing_subsequence(&current) {
+        stable[j] = true;
+    }
+
+    // each element that moves is placed directly after the element that precedes it in `target`
+    // stable elements are already in order, so once every other element has moved, the array is in the order of `target`
+    let mut slots = Slots::new(&current, &target, &stable);
+    for &j in &target {
+        if let Some((from, to)) = slots.move_to_target(j) {
+            results.push(Patch::Move {
+                from: root.child(from.to_string()),
+                path: root.child(to.to_string()),
+            });
+        }
+    }
+
+    // adding in increasing order means every element before `j` is already in place
+    let is_matched = {
+        let mut is_matched = vec![false; after.len()];
+        for &j in &target {
+            is_matched[j] = true;
+        }
+        is_matched
+    };
+    for (j, value) in after.iter().enumerate().filter(|(j, _)| !is_matched[*j]) {
+        results.push(Patch::Add {
+            path: root.child(j.to_string()),
+            value: value.clone(),
+        });
+    }
+
+    for (i, j) in matched
+        .iter()
+        .enumerate()
+        .filter_map(|(i, j)| j.map(|j| (i, j)))
+    {
+        results.diff(&before[i], &after[j], root.child(j.to_string()));
+    }
+
+    results.run(options, hashes)
+}
+
+/// The positions of the matched elements while they are moved into the order of `target`
+///
+/// Every element has a slot before it moves and a slot after, which are fixed up front: group `i + 1` starts with the
+/// slot of the element at index `i`, followed by the slots of the elements that are moved directly after it, and group 0
+/// holds the elements that are moved to the front. The index of an element is the number of occupied slots before its
+/// slot, which a Fenwick tree counts, so each move is O(log n) rather than shifting and searching the array
+struct Slots {
+    /// For each element of `after`, its slot before and after it moves
+    slots: Vec<(usize, usize)>,
+    /// A Fenwick tree of the number of occupied slots
+    occupied: Vec<usize>,
+}
+
+impl Slots {
+    fn new(current: &[usize], target: &[usize], stable: &[bool]) -> Self {
+        // every group has room for the element it starts with, even group 0, which is never occupied
+        let mut group_lens = vec![1; current.len() + 1];
+        let mut groups = vec![(0, 0); stable.len()];
+        for (i, &j) in current.iter().enumerate() {
+            groups[j].0 = i + 1;
+        }
+        let mut group = 0;
+        for &j in target {
+            if stable[j] {
+                group = groups[j].0;
+            } else {
+                groups[j].1 = group;
+            }
+            group_lens[group] += usize::from(!stable[j]);
+        }
+
+        let mut group_starts = vec![0; group_lens.len()];
+        for group in 1..group_lens.len() {
+            group_starts[group] = group_starts[group - 1] + group_lens[group - 1];
+        }
+        let mut next_free = group_starts
+            .iter()
+            .map(|start| start + 1)
+            .collect::<Vec<_>>();
+        let mut slots = vec![(0, 0); stable.len()];
+        for &j in target {
+            let (start, moved) = groups[j];
+            slots[j].0 = group_starts[start];
+            slots[j].1 = if stable[j] {
+                slots[j].0
+            } else {
+                next_free[moved] += 1;
+                next_free[moved] - 1
+            };
+        }
+
+        let mut positions = Self {
+            slots,
+            occupied: vec![0; group_starts[current.len()] + group_lens[current.len()] + 1],
+        };
+        for &j in current {
+            positions.update(positions.slots[j].0, true);
+        }
+        positions
+    }
+
+    /// Move `j` into its slot in `target`, returning its index before and after, unless it is already in place
+    fn move_to_target(&mut self, j: usize) -> Option<(usize, usize)> {
+        let (before, after) = self.slots[j];
+        if before == after {
+            return None;
+        }
+        let from = self.count_before(before);
+        self.update(before, false);
+        let to = self.count_before(after);
+        self.update(after, true);
+        Some((from, to)).filter(|(from, to)| from != to)
+    }
+
+    fn update(&mut self, slot: usize, occupied: bool) {
+        let mut i = slot + 1;
+        while i < self.occupied.len() {
+            if occupied {
+                self.occupied[i] += 1;
+            } else {
+                self.occupied[i] -= 1;
+            }
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn count_before(&self, slot: usize) -> usize {
+        let mut count = 0;
+        let mut i = slot;
+        while i > 0 {
+            count += self.occupied[i];
+            i -= i & i.wrapping_neg();
+        }
+        count
+    }
+}
+
+/// The values of a longest strictly increasing subsequence of `values`
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // `tails[k]` is the index of the smallest value that ends an increasing subsequence of length `k + 1`
+    let mut tails: Vec<usize> = vec![];
+    let mut previous = vec![None; values.len()];
+    for (index, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        if length > 0 {
+            previous[index] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut result = vec![];
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        result.push(values[index]);
+        next = previous[index];
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn identities_should_be_compared_by_value() {
+        let key = ArrayKey::field("id");
+        let before = vec![json!({"id": 1}), json!({"id": "1"}), json!({"id": [1.5]})];
+        let after = vec![json!({"id": [1.5]}), json!({"id": 1.0}), json!({"id": 1})];
+        assert_eq!(
+            match_elements(&before, &after, &key),
+            vec![Some(1), None, Some(0)]
+        );
+    }
+
+    #[test]
+    fn should_find_longest_increasing_subsequence() {
+        assert_eq!(longest_increasing_subsequence(&[]), Vec::<usize>::new());
+        assert_eq!(longest_increasing_subsequence(&[3, 0, 1, 2]), vec![0, 1, 2]);
+        assert_eq!(
+            longest_increasing_subsequence(&[0, 8, 4, 12, 2, 10, 6, 14, 1, 9]),
+            vec![0, 2, 6, 9]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Longest common subsequence of two slices, using Myers' O(ND) algorithm
+//!
+//! This follows the linear space "middle snake" variant, as used by diff-match-patch:
+//! the forward and reverse searches run simultaneously until they overlap, and the problem is split at the overlap.
+//! With the `rayon` feature, the two halves of a large problem are searched in parallel
+//!
+//! The search takes `O((N + M) * D)` comparisons, where `D` is the number of edits, so it gives up rather than let
+//! large slices that are mostly different take quadratic time
+
+/// The combined length of the slices above which, with the `rayon` feature, the two halves are searched in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_LEN: usize = 1024;
+
+/// Roughly how many comparisons the search may take before it gives up
+///
+/// This is far more than diffs of typical documents need: slices of 2000 elements that have nothing in common are
+/// still searched in full
+const MAX_COMPARISONS: usize = 1 << 24;
+
+/// The search was abandoned, since the slices need more edits than [MAX_COMPARISONS] allows for
+struct GaveUp;
+
+/// Find a longest common subsequence of `a` and `b`, as pairs of matching indices in increasing order
+///
+/// Returns `None` if `a` and `b` differ in so many elements that finding one would take too long
+pub(crate) fn lcs<T: Sync>(
+    a: &[T],
+    b: &[T],
+    eq: impl Fn(&T, &T) -> bool + Sync,
+) -> Option<Vec<(usize, usize)>> {
+    // the search for each number of edits compares up to every element once, so this bounds the total
+    let max_edits = MAX_COMPARISONS / (a.len() + b.len()).max(1);
+    let mut matches = vec![];
+    lcs_into(a, b, 0, 0, &eq, max_edits, &mut matches).ok()?;
+    Some(matches)
+}
+
+fn lcs_into<T: Sync>(
+    mut a: &[T],
+    mut b: &[T],
+    mut a_offset: usize,
+    mut b_offset: usize,
+    eq: &(impl Fn(&T, &T) -> bool + Sync),
+    max_edits: usize,
+    matches: &mut Vec<(usize, usize)>,
+) -> Result<(), GaveUp> {
+    // common prefixes and suffixes are very common in practice, and trimming them keeps the search small
+    while let (Some(x), Some(y)) = (a.first(), b.first()) {
+        if !eq(x, y) {
+            break;
+        }
+        matches.push((a_offset, b_offset));
+        a = &a[1..];
+        b = &b[1..];
+        a_offset += 1;
+        b_offset += 1;
+    }
+
+    let mut suffix = 0;
+    while suffix < a.len()
+        && suffix < b.len()
+        && eq(&a[a.len() - suffix - 1], &b[b.len() - suffix - 1])
+    {
+        suffix += 1;
+    }
+    let a_trimmed = &a[..a.len() - suffix];
+    let b_trimmed = &b[..b.len() - suffix];
+
+    if !a_trimmed.is_empty() && !b_trimmed.is_empty() {
+        if let Some((x, y)) = middle_snake(a_trimmed, b_trimmed, eq, max_edits)? {
+            search_halves(
+                (&a_trimmed[..x], &b_trimmed[..y], a_offset, b_offset),
+                (&a_trimmed[x..], &b_trimmed[y..], a_offset + x, b_offset + y),
+                eq,
+                max_edits,
+                matches,
+            )?;
+        }
+    }
+
+    let a_suffix = a_offset + a_trimmed.len();
+    let b_suffix = b_offset + b_trimmed.len();
+    matches.extend((0..suffix).map(|i| (a_suffix + i, b_suffix + i)));
+    Ok(())
+}
+
+/// A part of the problem: the two slices, and their offsets in the original slices
+type Half<'a, T> = (&'a [T], &'a [T], usize, usize);
+
+/// Search the two halves of a problem that was split, pushing the matches in order
+fn search_halves<T: Sync>(
+    first: Half<'_, T>,
+    second: Half<'_, T>,
+    eq: &(impl Fn(&T, &T) -> bool + Sync),
+    max_edits: usize,
+    matches: &mut Vec<(usize, usize)>,
+) -> Result<(), GaveUp> {
+    #[cfg(feature = "rayon")]
+    {
+        if first.0.len() + first.1.len() + second.0.len() + second.1.len() > PARALLEL_LEN {
+            let search = |(a, b, a_offset, b_offset): Half<'_, T>| {
+                let mut matches = vec![];
+                lcs_into(a, b, a_offset, b_offset, eq, max_edits, &mut matches).map(|_| matches)
+            };
+            let (first, second) = rayon::join(|| search(first), || search(second));
+            matches.extend(first?);
+            matches.extend(second?);
+            return Ok(());
+        }
+    }
+
+    let (a, b, a_offset, b_offset) = first;
+    lcs_into(a, b, a_offset, b_offset, eq, max_edits, matches)?;
+    let (a, b, a_offset, b_offset) = second;
+    lcs_into(a, b, a_offset, b_offset, eq, max_edits, matches)
+}
+
+/// Find a point on an optimal edit path where the problem can be split, or `None` if `a` and `b` have nothing in common
+///
+/// Gives up if the path needs more than `max_edits` edits
+fn middle_snake<T>(
+    a: &[T],
+    b: &[T],
+    eq: &impl Fn(&T, &T) -> bool,
+    max_edits: usize,
+) -> Result<Option<(usize, usize)>, GaveUp> {
+    // slices are never longer than `isize::MAX`, so these conversions are lossless
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    // diagonals range from `-max_d` to `max_d`, and the search looks one past either end
+    let len = 2 * max_d as usize + 2;
+
+    // the furthest reaching x on each diagonal k, for the forward and reverse searches
+    let mut forward = vec![-1; len];
+    let mut reverse = vec![-1; len];
+    forward[offset as usize + 1] = 0;
+    reverse[offset as usize + 1] = 0;
+
+    let delta = n - m;
+    // if the total number of edits is odd, the forward search finds the overlap, otherwise the reverse search does
+    let front = delta % 2 != 0;
+
+    // diagonals that have run off the edge of the grid don't need to be searched again
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    // each step of the search covers one more edit at either end of the path
+    let limit = max_d.min((max_edits / 2) as isize + 1);
+    for d in 0..limit {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let k1_offset = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[k1_offset - 1] < forward[k1_offset + 1])
+            {
+                forward[k1_offset + 1]
+            } else {
+                forward[k1_offset - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && eq(&a[x1 as usize], &b[y1 as usize]) {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[k1_offset] = x1;
+
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2_offset = offset + delta - k1;
+                if k2_offset >= 0 && (k2_offset as usize) < len && reverse[k2_offset as usize] != -1
+                {
+                    let x2 = n - reverse[k2_offset as usize];
+                    if x1 >= x2 {
+                        return Ok(Some((x1 as usize, y1 as usize)));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let k2_offset = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && reverse[k2_offset - 1] < reverse[k2_offset + 1])
+            {
+                reverse[k2_offset + 1]
+            } else {
+                reverse[k2_offset - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && eq(&a[(n - x2 - 1) as usize], &b[(m - y2 - 1) as usize]) {
+                x2 += 1;
+                y2 += 1;
+            }
+            reverse[k2_offset] = x2;
+
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1_offset = offset + delta - k2;
+                if k1_offset >= 0 && (k1_offset as usize) < len && forward[k1_offset as usize] != -1
+                {
+                    let x1 = forward[k1_offset as usize];
+                    let y1 = offset + x1 - k1_offset;
+                    if x1 >= n - x2 {
+                        return Ok(Some((x1 as usize, y1 as usize)));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+
+    if limit < max_d {
+        Err(GaveUp)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    // the classic quadratic dynamic programming solution, which is obviously correct
+    // only the previous row of the table is needed, so large inputs can be checked too
+    fn lcs_len<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+        let mut previous = vec![0; b.len() + 1];
+        for x in a {
+            let mut row = vec![0; b.len() + 1];
+            for (j, y) in b.iter().enumerate() {
+                row[j + 1] = if x == y {
+                    previous[j] + 1
+                } else {
+                    previous[j + 1].max(row[j])
+                };
+            }
+            previous = row;
+        }
+        previous[b.len()]
+    }
+
+    #[test]
+    fn should_find_common_subsequence() {
+        let a = b"ABCABBA";
+        let b = b"CBABAC";
+        let matches = lcs(a, b, |x, y| x == y).unwrap();
+        assert_eq!(matches.len(), 4);
+        assert_eq!(lcs(b"", b"abc", |x, y| x == y), Some(vec![]));
+        assert_eq!(lcs(b"abc", b"xyz", |x, y| x == y), Some(vec![]));
+        assert_eq!(
+            lcs(b"abc", b"abc", |x, y| x == y),
+            Some(vec![(0, 0), (1, 1), (2, 2)])
+        );
+    }
+
+    #[test]
+    fn large_inputs_should_find_common_subsequence() {
+        // long enough to be split and searched in parallel with the `rayon` feature
+        let a = (0..3000u32).map(|i| i % 7).collect::<Vec<_>>();
+        let b = (0..3000u32)
+            .map(|i| if i % 10 == 0 { (i * 3) % 7 } else { i % 7 })
+            .collect::<Vec<_>>();
+        let matches = lcs(&a, &b, |x, y| x == y).unwrap();
+        for window in matches.windows(2) {
+            assert!(window[0].0 < window[1].0 && window[0].1 < window[1].1);
+        }
+        for &(i, j) in &matches {
+            assert_eq!(a[i], b[j]);
+        }
+        let rough = lcs(&a[..300], &b[..300], |x, y| x == y).unwrap().len();
+        assert!(
+            matches.len() >= rough * 9,
+            "{} < {}",
+            matches.len(),
+            rough * 9
+        );
+    }
+
+    #[test]
+    fn should_give_up_on_large_inputs_with_many_edits() {
+        let a = (0..5000).collect::<Vec<_>>();
+        let b = (5000..10000).collect::<Vec<_>>();
+        assert_eq!(lcs(&a, &b, |x, y| x == y), None);
+
+        // a few edits are still found, however large the inputs
+        let mut c = a.clone();
+        c[2500] = -1;
+        c.insert(100, -2);
+        let matches = lcs(&a, &c, |x, y| x == y).unwrap();
+        assert_eq!(matches.len(), a.len() - 1);
+
+        // small inputs are always searched in full
+        let b = (2000..4000).collect::<Vec<_>>();
+        assert_eq!(lcs(&a[..2000], &b, |x, y| x == y), Some(vec![]));
+    }
+
+    proptest! {
+        #[test]
+        fn lcs_is_a_longest_common_subsequence(
+            a in prop::collection::vec(0u8..4, 0..40),
+            b in prop::collection::vec(0u8..4, 0..40),
+        ) {
+            let matches = lcs(&a, &b, |x, y| x == y).unwrap();
+            for window in matches.windows(2) {
+                prop_assert!(window[0].0 < window[1].0 && window[0].1 < window[1].1);
+            }
+            for &(i, j) in &matches {
+                prop_assert_eq!(a[i], b[j]);
+            }
+            prop_assert_eq!(matches.len(), lcs_len(&a, &b));
+        }
+    }
+}
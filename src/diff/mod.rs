@@ -1,8 +1,26 @@
-use std::cmp::min;
+mod cost;
+mod hash;
+mod iter;
+mod keyed;
+mod lcs;
+mod moves;
+mod options;
+mod steps;
+
+use std::{cmp::min, iter::once};
 
 use serde_json::{Map, Value};
 
-use crate::{Patch, Path};
+use crate::{patch::walk::walk, ApplyOptions, Patch, Path};
+
+use cost::Costed;
+use hash::Hashes;
+use steps::Steps;
+
+pub use cost::CostModel;
+pub use iter::diff_iter;
+pub use keyed::ArrayKey;
+pub use options::{ArrayStrategy, DiffOptions};
 
 /// Compute the diff between two JSON Documents
 ///
@@ -19,106 +37,278 @@ use crate::{Patch, Path};
 /// let after_again = apply(before, patches).unwrap();
 /// assert_eq!(after, after_again);
 /// ```
+///
+/// The patches are in a canonical order, which doesn't depend on whether serde_json's `preserve_order` feature is enabled.
+/// Within each object, members that are removed come first, then members that are added, then changes inside the members that
+/// are in both, each in order of their keys. Within each array, patches are in order of the indices they apply to
+///
+/// No [CostModel] is used, so a changed object or array is only replaced as a whole when its type changes, however many
+/// patches its contents need. Use [diff_with] with [DiffOptions::cost_model] to replace values when that is cheaper
 pub fn diff(before: &Value, after: &Value) -> Vec<Patch> {
-    diff_with_root(before, after, Path::root())
+    diff_with(before, after, &DiffOptions::default())
 }
 
-fn diff_with_root(before: &Value, after: &Value, root: Path) -> Vec<Patch> {
+/// Compute the diff between two JSON Documents, using the given [DiffOptions]
+pub fn diff_with(before: &Value, after: &Value, options: &DiffOptions) -> Vec<Patch> {
+    let hashes = Hashes::new(before, after);
+    let mut patches = diff_with_root(before, after, Path::root(), options, &hashes).patches;
+    if options.detect_moves || options.detect_copies {
+        patches = moves::detect_moves_and_copies(before, after, patches, options, &hashes);
+    }
+
+    // there's nothing to guard if nothing changed
+    if let (Some(path), false) = (&options.version_guard, patches.is_empty()) {
+        patches.insert(0, version_guard(before, path));
+    }
+    patches
+}
+
+/// A test of the value at `path` in `before`
+///
+/// If there is no value at `path`, the closest parent that exists is tested instead, which only passes while `path` is
+/// still missing. The root always exists, so there is always something to test
+fn version_guard(before: &Value, path: &Path) -> Patch {
+    (0..=path.len())
+        .rev()
+        .map(|len| path.prefix(len))
+        .find_map(|path| {
+            let value = walk(before, &path, &ApplyOptions::default()).ok()?.clone();
+            Some(Patch::Test { path, value })
+        })
+        .expect("the root always exists")
+}
+
+fn diff_with_root(
+    before: &Value,
+    after: &Value,
+    root: Path,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
     use Value::*;
-    match (before, after) {
-        (Object(before), Object(after)) => diff_maps(before, after, root),
-        (Array(before), Array(after)) => diff_vecs(before, after, root),
-        (before, after) => {
-            if before == after {
-                vec![]
-            } else {
-                vec![Patch::Replace {
-                    path: root,
-                    value: after.to_owned(),
-                }]
-            }
+    if options.is_ignored(&root) || hashes.eq(before, after) {
+        return Costed::default();
+    }
+    // replacing a value as a whole would overwrite the ignored values below it, so those are diffed instead
+    if options.is_too_deep(&root) && !options.contains_ignored(&root) {
+        return Costed::new(replace(before, after, root, options), options);
+    }
+
+    let patches = match (before, after) {
+        (Object(before), Object(after)) => diff_maps(before, after, &root, options, hashes),
+        (Array(before), Array(after)) => diff_vecs(before, after, &root, options, hashes),
+        (before, after) => return Costed::new(replace(before, after, root, options), options),
+    };
+    cost::cheapest(before, after, root, patches, options)
+}
+
+/// Replace `before` with `after` as a whole, which are known to differ
+fn replace(before: &Value, after: &Value, root: Path, options: &DiffOptions) -> Vec<Patch> {
+    let mut results = vec![];
+    push_guard(&mut results, &root, before, options);
+    results.push(Patch::Replace {
+        path: root,
+        value: after.to_owned(),
+    });
+    results
+}
+
+fn push_remove(results: &mut impl Extend<Patch>, path: Path, old: &Value, options: &DiffOptions) {
+    push_guard(results, &path, old, options);
+    results.extend(once(Patch::Remove { path }));
+}
+
+// guards are placed directly before the operation that overwrites `old`, so `path` refers to the same location
+fn push_guard(results: &mut impl Extend<Patch>, path: &Path, old: &Value, options: &DiffOptions) {
+    if options.test_guards {
+        results.extend(once(Patch::Test {
+            path: path.clone(),
+            value: old.clone(),
+        }));
+    }
+}
+
+fn diff_maps(
+    before: &Map<String, Value>,
+    after: &Map<String, Value>,
+    root: &Path,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
+    let before_members = sorted_members(before);
+    let after_members = sorted_members(after);
+
+    let members_to_remove = before_members
+        .iter()
+        .copied()
+        .filter(|(key, _)| !after.contains_key(*key));
+    let members_to_add = after_members
+        .iter()
+        .copied()
+        .filter(|(key, _)| !before.contains_key(*key));
+    // unchanged members are skipped here, which saves building their paths
+    let changed_members = before_members
+        .iter()
+        .copied()
+        .filter_map(|(key, value)| Some((key, value, after.get(key)?)))
+        .filter(|(_, before, after)| !hashes.eq(before, after));
+
+    let mut results = Steps::new();
+
+    for (key, value) in members_to_remove {
+        let path = root.child(key);
+        if !options.is_ignored(&path) {
+            push_remove(&mut results, path, value, options);
+        }
+    }
+
+    for (key, value) in members_to_add {
+        let path = root.child(key);
+        if !options.is_ignored(&path) {
+            results.push(Patch::Add {
+                path,
+                value: value.clone(),
+            });
         }
     }
+
+    for (key, before, after) in changed_members {
+        results.diff(before, after, root.child(key));
+    }
+
+    results.run(options, hashes)
 }
 
-fn diff_maps(before: &Map<String, Value>, after: &Map<String, Value>, root: Path) -> Vec<Patch> {
-    if before == after {
-        return vec![];
+/// The members of `map`, in order of their keys
+///
+/// The order a map iterates in depends on whether serde_json's `preserve_order` feature is enabled, which may be done by
+/// another crate, so this is used to make the order of the patches canonical
+fn sorted_members(map: &Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut members = map.iter().collect::<Vec<_>>();
+    members.sort_unstable_by_key(|(key, _)| *key);
+    members
+}
+
+fn diff_vecs(
+    before: &[Value],
+    after: &[Value],
+    root: &Path,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
+    if let Some(key) = options.array_key_for(root) {
+        return keyed::diff_vecs_by_key(before, after, root, key, options, hashes);
     }
 
-    let keys_to_remove = before.keys().filter(|key| !after.contains_key(*key));
-    let keys_to_add = after.keys().filter(|key| !before.contains_key(*key));
-    let shared_keys = before.keys().filter(|key| after.contains_key(*key));
+    match options.array_strategy {
+        ArrayStrategy::Lcs => diff_vecs_by_lcs(before, after, root, options, hashes),
+        ArrayStrategy::Index => diff_vecs_by_index(before, after, root, options, hashes),
+    }
+}
 
-    let mut results = vec![];
+// elements that aren't part of the longest common subsequence are removed or added around it
+// the indices in the emitted patches refer to the array as it is being patched, not to `before` or `after`
+fn diff_vecs_by_lcs(
+    before: &[Value],
+    after: &[Value],
+    root: &Path,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
+    let matches = match lcs::lcs(before, after, |a, b| hashes.eq(a, b)) {
+        Some(matches) => matches,
+        // the arrays are large and mostly different, so matching up their elements isn't worth the time
+        None => return diff_vecs_by_index(before, after, root, options, hashes),
+    };
 
-    results.extend(keys_to_remove.map(|key| Patch::Remove {
-        path: root.clone().join(key),
-    }));
+    let mut results = Steps::new();
+    let mut index = 0;
+    let (mut i, mut j) = (0, 0);
 
-    results.extend(keys_to_add.map(|key| Patch::Add {
-        path: root.clone().join(key),
-        value: after.get(key).unwrap().clone(),
-    }));
+    // the sentinel at the end handles any elements after the last match
+    for (next_i, next_j) in matches.into_iter().chain(once((before.len(), after.len()))) {
+        // `before[i..next_i]` is replaced by `after[j..next_j]`
+        let removed = next_i - i;
+        let added = next_j - j;
 
-    results.extend(shared_keys.flat_map(|key| {
-        diff_with_root(
-            before.get(key).unwrap(),
-            after.get(key).unwrap(),
-            root.clone().join(key),
-        )
-    }));
+        // a removal followed by an addition at the same index is a change to that element, so diff it instead
+        let changed = if options.prefer_replace {
+            min(removed, added)
+        } else {
+            0
+        };
+        for offset in 0..changed {
+            let path = root.child(index.to_string());
+            results.diff(&before[i + offset], &after[j + offset], path);
+            index += 1;
+        }
 
-    results
+        for offset in changed..removed {
+            let path = root.child(index.to_string());
+            push_remove(&mut results, path, &before[i + offset], options);
+        }
+
+        for offset in changed..added {
+            results.push(Patch::Add {
+                path: root.child(index.to_string()),
+                value: after[j + offset].clone(),
+            });
+            index += 1;
+        }
+
+        // skip over the matching element
+        index += 1;
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+
+    results.run(options, hashes)
 }
 
-// this uses a bad approach
-// it essentially treats the vec like an object with integer keys
+// this essentially treats the vec like an object with integer keys
 // works well if appending to the list, only the new index has changed
 // pretty bad if you insert into the start of the list, since every index will have its corresponding value changed, so gets a patch emitted for it
-fn diff_vecs(before: &[Value], after: &[Value], root: Path) -> Vec<Patch> {
-    if before == after {
-        return vec![];
-    }
-
-    let mut results = vec![];
+fn diff_vecs_by_index(
+    before: &[Value],
+    after: &[Value],
+    root: &Path,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Costed {
+    let mut results = Steps::new();
 
     let shared_indices = 0..(min(before.len(), after.len()));
 
     for index in shared_indices {
-        if before[index] != after[index] {
-            results.extend(diff_with_root(
-                &before[index],
-                &after[index],
-                root.clone().join(index.to_string()),
-            ));
+        if !hashes.eq(&before[index], &after[index]) {
+            let path = root.child(index.to_string());
+            results.diff(&before[index], &after[index], path);
         }
     }
 
-    if before.len() > after.len() {
-        let indices_to_remove = after.len()..before.len();
-        results.extend(indices_to_remove.map(|_| Patch::Remove {
-            path: root.clone().join(after.len().to_string()), // always use the first index so we can sequentially remove
-        }))
+    for old in before.iter().skip(after.len()) {
+        let path = root.child(after.len().to_string()); // always use the first index so we can sequentially remove
+        push_remove(&mut results, path, old, options);
     }
 
     if after.len() > before.len() {
         let indices_to_add = before.len()..after.len();
         results.extend(indices_to_add.map(|i| Patch::Add {
-            path: root.clone().join(i.to_string()),
+            path: root.child(i.to_string()),
             value: after[i].clone(),
         }))
     }
 
-    results
+    results.run(options, hashes)
 }
 
 #[cfg(test)]
 mod test {
 
+    use proptest::prelude::*;
     use serde_json::json;
 
-    use crate::apply;
+    use crate::{apply, Error};
 
     use super::*;
 
@@ -127,10 +317,53 @@ mod test {
     // we can use this to generate some tests
     // note these tests aren't 100% meaningful, since a patch that simply replaces the entire document would be pass the test, but be "incorrect" in a sense
     // they also implicitly depend on `apply` being correct
-    fn test_round_trip(before: Value, after: Value) {
-        let patches = diff(&before, &after);
+    //
+    // ignored paths are the exception, since changes to them are deliberately left out
+    // instead, diffing the result against `after` again should find nothing that isn't ignored
+    fn test_round_trip(before: Value, after: Value, options: &DiffOptions) {
+        let patches = diff_with(&before, &after, options);
         let computed_after = apply(before, patches).unwrap();
-        assert_eq!(after, computed_after)
+        if options.ignored.is_empty() {
+            assert_eq!(after, computed_after)
+        } else {
+            assert_eq!(diff_with(&computed_after, &after, options), vec![])
+        }
+    }
+
+    // every option is exercised by at least one of these
+    fn option_sets() -> Vec<DiffOptions> {
+        vec![
+            DiffOptions::default(),
+            DiffOptions::default().array_strategy(ArrayStrategy::Index),
+            DiffOptions::default().prefer_replace(false),
+            DiffOptions::default().max_depth(0),
+            DiffOptions::default().max_depth(2),
+            DiffOptions::default()
+                .ignore(Path::new("/hello"))
+                .ignore(Path::new("/1"))
+                .ignore(Path::new("/x/0/0")),
+            DiffOptions::default().test_guards(true),
+            DiffOptions::default().version_guard(Path::new("/name")),
+            DiffOptions::default()
+                .detect_moves(true)
+                .detect_copies(true)
+                .test_guards(true),
+            DiffOptions::default()
+                .array_key(
+                    Path::root(),
+                    ArrayKey::with(|value| Some(json!(value.is_array()))),
+                )
+                .array_key(
+                    Path::new("/pets"),
+                    ArrayKey::with(|value| Some(value.clone())),
+                ),
+            DiffOptions::default()
+                .cost_model(CostModel::Size)
+                .test_guards(true),
+            DiffOptions::default()
+                .cost_model(CostModel::with(|_| 1))
+                .ignore(Path::new("/x/0/hello")),
+        ]
     }
 
     // generate a list of example jsons, we can then take the cartesian cross product and check every round trip works
@@ -168,9 +401,562 @@ mod test {
                 .iter()
                 .map(move |after| (before.clone(), after.clone()))
         });
-        for (before, after) in pairs {
-            println!("comparing: {:?} and {:?}", before, after);
-            test_round_trip(before, after);
+        for options in option_sets() {
+            for (before, after) in pairs.clone() {
+                println!("comparing: {:?} and {:?} with {:?}", before, after, options);
+                test_round_trip(before.clone(), after.clone(), &options);
+                // nesting the documents exercises paths that aren't the root
+                test_round_trip(
+                    json!({"x": [before, 1], "y": after}),
+                    json!({"x": [after, 2], "z": before}),
+                    &options,
+                );
+            }
+        }
+    }
+
+    fn assert_iter_agrees(before: &Value, after: &Value) {
+        let lazy = diff_iter(before, after)
+            .map(|patch| patch.to_patch())
+            .collect::<Vec<_>>();
+        assert_eq!(lazy, diff(before, after));
+    }
+
+    #[test]
+    fn diff_iter_should_agree_with_diff() {
+        let jsons = example_jsons();
+        for before in &jsons {
+            for after in &jsons {
+                assert_iter_agrees(before, after);
+                assert_iter_agrees(
+                    &json!({"x": [before, 1], "y": after}),
+                    &json!({"x": [after, 2], "z": before}),
+                );
+            }
+        }
+    }
+
+    // two clients start from the same document, and both send patches computed against it
+    // the first patch is applied, and the second patch should only apply if it doesn't overwrite the first
+    fn apply_concurrently(
+        base: &Value,
+        first: &Value,
+        second: &Value,
+        options: &DiffOptions,
+    ) -> Result<Value, Error> {
+        let current = apply(base.clone(), diff_with(base, first, options)).unwrap();
+        apply(current, diff_with(base, second, options))
+    }
+
+    fn is_failed_test(result: Result<Value, Error>) -> bool {
+        matches!(
+            result.map_err(|e| e.root_cause().clone()),
+            Err(Error::FailedTest { .. })
+        )
+    }
+
+    #[test]
+    fn guards_should_detect_concurrent_edits() {
+        let base =
+            json!({"version": 1, "title": "a", "tags": ["x", "y"], "body": {"text": "hello"}});
+        let guarded = DiffOptions::default().test_guards(true);
+
+        // both clients change the same value
+        let first =
+            json!({"version": 1, "title": "b", "tags": ["x", "y"], "body": {"text": "hello"}});
+        let second =
+            json!({"version": 1, "title": "c", "tags": ["x", "y"], "body": {"text": "hello"}});
+        assert_eq!(
+            apply_concurrently(&base, &first, &second, &DiffOptions::default()),
+            Ok(second.clone())
+        );
+        assert!(is_failed_test(apply_concurrently(
+            &base, &first, &second, &guarded
+        )));
+
+        // one client removes what the other changes
+        let first =
+            json!({"version": 1, "title": "a", "tags": ["x", "z"], "body": {"text": "hello"}});
+        let second = json!({"version": 1, "title": "a", "tags": ["x"], "body": {"text": "hello"}});
+        assert!(is_failed_test(apply_concurrently(
+            &base, &first, &second, &guarded
+        )));
+
+        // one client replaces a whole object that the other changed inside
+        let first =
+            json!({"version": 1, "title": "a", "tags": ["x", "y"], "body": {"text": "bye"}});
+        let second = json!({"version": 1, "title": "a", "tags": ["x", "y"], "body": null});
+        assert!(is_failed_test(apply_concurrently(
+            &base, &first, &second, &guarded
+        )));
+
+        // edits to different values don't conflict
+        let first =
+            json!({"version": 1, "title": "b", "tags": ["x", "y"], "body": {"text": "hello"}});
+        let second =
+            json!({"version": 1, "title": "a", "tags": ["x", "y"], "body": {"text": "bye"}});
+        assert_eq!(
+            apply_concurrently(&base, &first, &second, &guarded),
+            Ok(json!({"version": 1, "title": "b", "tags": ["x", "y"], "body": {"text": "bye"}}))
+        );
+    }
+
+    #[test]
+    fn version_guard_should_detect_any_concurrent_edit() {
+        let base = json!({"version": 1, "title": "a", "body": "hello"});
+        let first = json!({"version": 2, "title": "b", "body": "hello"});
+        let second = json!({"version": 2, "title": "a", "body": "bye"});
+        let options = DiffOptions::default().version_guard(Path::new("/version"));
+
+        assert!(is_failed_test(apply_concurrently(
+            &base, &first, &second, &options
+        )));
+        assert_eq!(
+            apply(base.clone(), diff_with(&base, &second, &options)),
+            Ok(second)
+        );
+
+        // without any changes, there is nothing to guard
+        assert_eq!(diff_with(&base, &base, &options), vec![]);
+    }
+
+    #[test]
+    fn version_guard_should_test_the_parent_of_a_missing_version() {
+        let unversioned = json!({"title": "a", "meta": {}});
+        let changed = json!({"title": "b", "meta": {}});
+
+        let options = DiffOptions::default().version_guard(Path::new("/version"));
+        let patches = diff_with(&unversioned, &changed, &options);
+        assert_eq!(
+            patches[0],
+            Patch::Test {
+                path: Path::root(),
+                value: unversioned.clone(),
+            }
+        );
+        assert_eq!(
+            apply(unversioned.clone(), patches.clone()),
+            Ok(changed.clone())
+        );
+        let versioned = json!({"title": "a", "meta": {}, "version": 1});
+        assert!(is_failed_test(apply(versioned, patches)));
+
+        let options = DiffOptions::default().version_guard(Path::new("/meta/version/major"));
+        assert_eq!(
+            diff_with(&unversioned, &changed, &options)[0],
+            Patch::Test {
+                path: Path::new("/meta"),
+                value: json!({}),
+            }
+        );
+    }
+
+    #[test]
+    fn options_should_change_the_diff() {
+        let before = json!({"a": {"b": [1, 2, 3]}, "c": 1});
+        let after = json!({"a": {"b": [1, 4, 3]}, "c": 2});
+
+        let patches = |options: DiffOptions| diff_with(&before, &after, &options);
+        assert_eq!(
+            patches(DiffOptions::default()),
+            vec![
+                Patch::Replace {
+                    path: Path::new("/a/b/1"),
+                    value: json!(4),
+                },
+                Patch::Replace {
+                    path: Path::new("/c"),
+                    value: json!(2),
+                },
+            ]
+        );
+        assert_eq!(
+            patches(DiffOptions::default().prefer_replace(false))[..2],
+            [
+                Patch::Remove {
+                    path: Path::new("/a/b/1"),
+                },
+                Patch::Add {
+                    path: Path::new("/a/b/1"),
+                    value: json!(4),
+                },
+            ]
+        );
+        assert_eq!(
+            patches(DiffOptions::default().max_depth(1))[0],
+            Patch::Replace {
+                path: Path::new("/a"),
+                value: json!({"b": [1, 4, 3]}),
+            }
+        );
+        assert_eq!(
+            patches(DiffOptions::default().max_depth(0)),
+            vec![Patch::Replace {
+                path: Path::root(),
+                value: after.clone(),
+            }]
+        );
+        assert_eq!(
+            patches(DiffOptions::default().ignore(Path::new("/a"))),
+            vec![Patch::Replace {
+                path: Path::new("/c"),
+                value: json!(2),
+            }]
+        );
+        assert_eq!(
+            patches(DiffOptions::default().test_guards(true))[..2],
+            [
+                Patch::Test {
+                    path: Path::new("/a/b/1"),
+                    value: json!(2),
+                },
+                Patch::Replace {
+                    path: Path::new("/a/b/1"),
+                    value: json!(4),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn max_depth_should_not_overwrite_ignored_values() {
+        let before = json!({"a": {"x": 1, "ts": 1}, "b": {"y": 1}});
+        let after = json!({"a": {"x": 2, "ts": 2}, "b": {"y": 2}});
+        let options = DiffOptions::default()
+            .max_depth(1)
+            .ignore(Path::new("/a/ts"));
+        assert_eq!(
+            diff_with(&before, &after, &options),
+            vec![
+                Patch::Replace {
+                    path: Path::new("/a/x"),
+                    value: json!(2),
+                },
+                Patch::Replace {
+                    path: Path::new("/b"),
+                    value: json!({"y": 2}),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cost_model_should_pick_the_cheaper_patches() {
+        let before = json!({"a": {"b": 1, "c": 2, "d": 3}, "e": {"f": 1, "g": "a long string, which makes replacing the root expensive"}});
+        let after = json!({"a": {"b": 4, "c": 5, "d": 6}, "e": {"f": 2, "g": "a long string, which makes replacing the root expensive"}});
+        let options = DiffOptions::default().cost_model(CostModel::Size);
+        assert_eq!(
+            diff_with(&before, &after, &options),
+            vec![
+                Patch::Replace {
+                    path: Path::new("/a"),
+                    value: json!({"b": 4, "c": 5, "d": 6}),
+                },
+                Patch::Replace {
+                    path: Path::new("/e/f"),
+                    value: json!(2),
+                },
+            ]
+        );
+
+        // every patch costs the same, so the fewest patches are the cheapest
+        let options = DiffOptions::default().cost_model(CostModel::with(|_| 1));
+        assert_eq!(
+            diff_with(&before, &after, &options),
+            vec![Patch::Replace {
+                path: Path::root(),
+                value: after.clone(),
+            }]
+        );
+
+        // the ignored value must not be overwritten by a replacement of its parent
+        let options = options.ignore(Path::new("/a/d"));
+        assert_eq!(
+            diff_with(&before, &after, &options),
+            vec![
+                Patch::Replace {
+                    path: Path::new("/a/b"),
+                    value: json!(4),
+                },
+                Patch::Replace {
+                    path: Path::new("/a/c"),
+                    value: json!(5),
+                },
+                Patch::Replace {
+                    path: Path::new("/e/f"),
+                    value: json!(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lcs_should_insert_and_remove_at_the_right_index() {
+        let before = Value::Array((0..10_000).map(Value::from).collect());
+        let mut after = before.clone();
+        after.as_array_mut().unwrap().insert(0, json!("new"));
+        assert_eq!(
+            diff(&before, &after),
+            vec![Patch::Add {
+                path: Path::new("/0"),
+                value: json!("new"),
+            }]
+        );
+
+        assert_eq!(
+            diff(&json!([1, 2, 3, 4, 5]), &json!([1, 3, 4, 6, 5, 7])),
+            vec![
+                Patch::Remove {
+                    path: Path::new("/1"),
+                },
+                Patch::Add {
+                    path: Path::new("/3"),
+                    value: json!(6),
+                },
+                Patch::Add {
+                    path: Path::new("/5"),
+                    value: json!(7),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lcs_should_diff_changed_elements() {
+        let before = json!([{"a": 1}, {"b": 1}, {"c": 1}]);
+        let after = json!([{"a": 1}, {"b": 2}, {"c": 1}]);
+        assert_eq!(
+            diff(&before, &after),
+            vec![Patch::Replace {
+                path: Path::new("/1/b"),
+                value: json!(2),
+            }]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn array_diffs_round_trip(
+            before in prop::collection::vec(0u8..4, 0..20),
+            after in prop::collection::vec(0u8..4, 0..20),
+        ) {
+            let before = json!(before);
+            let after = json!(after);
+            for strategy in [ArrayStrategy::Lcs, ArrayStrategy::Index] {
+                let options = DiffOptions::default().array_strategy(strategy);
+                let patches = diff_with(&before, &after, &options);
+                prop_assert_eq!(apply(before.clone(), patches), Ok(after.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn keyed_arrays_should_match_elements_by_identity() {
+        let options = DiffOptions::default()
+            .array_key(Path::new("/records"), ArrayKey::field("id"))
+            .array_key(
+                Path::root(),
+                ArrayKey::with(|value| value.as_str().map(|s| json!(s.len()))),
+            );
+
+        let before = json!({"records": [{"id": 1}, {"id": 2, "x": 1}, {"id": 3}, {"id": 4}]});
+        let after = json!({"records": [{"id": 4}, {"id": 5}, {"id": 1}, {"id": 2, "x": 2}]});
+        let patches = diff_with(&before, &after, &options);
+        assert_eq!(
+            patches,
+            vec![
+                Patch::Remove {
+                    path: Path::new("/records/2"),
+                },
+                Patch::Move {
+                    from: Path::new("/records/2"),
+                    path: Path::new("/records/0"),
+                },
+                Patch::Add {
+                    path: Path::new("/records/1"),
+                    value: json!({"id": 5}),
+                },
+                Patch::Replace {
+                    path: Path::new("/records/3/x"),
+                    value: json!(2),
+                },
+            ]
+        );
+        assert_eq!(apply(before, patches), Ok(after));
+
+        // strings are identified by their length, and elements without an identity are never matched
+        let before = json!(["a", "bb", 1, "ccc"]);
+        let after = json!(["xxx", 1, "y"]);
+        let patches = diff_with(&before, &after, &options);
+        assert_eq!(apply(before, patches), Ok(after));
+    }
+
+    proptest! {
+        #[test]
+        fn keyed_array_diffs_round_trip(
+            before in prop::collection::vec((0u8..8, 0u8..2), 0..12),
+            after in prop::collection::vec((0u8..8, 0u8..2), 0..12),
+        ) {
+            let to_records = |records: Vec<(u8, u8)>| {
+                Value::Array(records.into_iter().map(|(id, x)| json!({"id": id, "x": x})).collect())
+            };
+            let before = to_records(before);
+            let after = to_records(after);
+            let options = DiffOptions::default().array_key(Path::root(), ArrayKey::field("id"));
+            let patches = diff_with(&before, &after, &options);
+            prop_assert_eq!(apply(before.clone(), patches), Ok(after.clone()));
+        }
+    }
+
+    #[test]
+    fn should_detect_moves_and_copies() {
+        let options = DiffOptions::default()
+            .detect_moves(true)
+            .detect_copies(true);
+        let moved = json!({"deep": {"values": [1, 2, 3]}});
+        let copied = json!({"other": [4, 5]});
+
+        let before = json!({"a": {"old": moved}, "b": [moved], "c": copied});
+        let after = json!({"a": {"new": moved}, "b": [], "c": copied, "d": copied});
+        let patches = diff_with(&before, &after, &options);
+        assert_eq!(
+            patches,
+            vec![
+                Patch::Copy {
+                    from: Path::new("/c"),
+                    path: Path::new("/d"),
+                },
+                Patch::Move {
+                    from: Path::new("/a/old"),
+                    path: Path::new("/a/new"),
+                },
+                // array elements are never moved, since their indices could shift
+                Patch::Remove {
+                    path: Path::new("/b/0"),
+                },
+            ]
+        );
+        assert_eq!(apply(before, patches), Ok(after));
+    }
+
+    #[test]
+    fn guards_should_move_with_the_values_they_guard() {
+        let options = DiffOptions::default().detect_moves(true).test_guards(true);
+        let before = json!({"": {"~0": 2}});
+        let after = json!({"": {}, "-": 2});
+        let patches = diff_with(&before, &after, &options);
+        assert_eq!(
+            patches,
+            vec![
+                Patch::Test {
+                    path: Path::new("//~00"),
+                    value: json!(2),
+                },
+                Patch::Move {
+                    from: Path::new("//~00"),
+                    path: Path::new("/-"),
+                },
+            ]
+        );
+        assert_eq!(apply(before, patches), Ok(after));
+    }
+
+    #[test]
+    fn move_and_copy_detection_should_round_trip() {
+        let options = DiffOptions::default()
+            .detect_moves(true)
+            .detect_copies(true);
+        let jsons = example_jsons();
+        for before in &jsons {
+            for after in &jsons {
+                let after = json!({"x": after, "y": before, "z": [before, after]});
+                let before = json!({"w": before, "z": [after]});
+                let patches = diff_with(&before, &after, &options);
+                assert_eq!(apply(before.clone(), patches), Ok(after.clone()));
+            }
+        }
+    }
+
+    // small documents with few distinct keys and values, so that subtrees are often shared between `before` and `after`
+    fn arb_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![Just(json!(null)), (0..3).prop_map(Value::from)];
+        leaf.prop_recursive(4, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::btree_map("[abc]", inner, 0..4)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    // keys that need escaping, or look like array indices, along with arbitrary unicode
+    fn arb_key() -> impl Strategy<Value = String> {
+        prop_oneof![
+            prop::sample::select(vec!["", "-", "0", "/", "~", "~0", "~1", "a/b", "~01", "/~"])
+                .prop_map(String::from),
+            "\\PC{0,4}",
+        ]
+    }
+
+    fn arb_value_with_keys() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![Just(json!(null)), (0..3).prop_map(Value::from)];
+        leaf.prop_recursive(4, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::btree_map(arb_key(), inner, 0..4)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    #[test]
+    fn keys_should_not_be_unescaped() {
+        let before = json!({"a/b": 1, "~1": {"~0": 1}, "": 1, "-": [1]});
+        let after = json!({"a/b": 2, "~1": {"~0": 2}, "": 2, "-": [1, 2]});
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                Patch::Replace {
+                    path: Path::new("/"),
+                    value: json!(2),
+                },
+                Patch::Add {
+                    path: Path::new("/-/1"),
+                    value: json!(2),
+                },
+                Patch::Replace {
+                    path: Path::new("/a~1b"),
+                    value: json!(2),
+                },
+                Patch::Replace {
+                    path: Path::new("/~01/~00"),
+                    value: json!(2),
+                },
+            ]
+        );
+        test_round_trip(before.clone(), after.clone(), &DiffOptions::default());
+        assert_iter_agrees(&before, &after);
+    }
+
+    proptest! {
+        #[test]
+        fn random_keys_round_trip(before in arb_value_with_keys(), after in arb_value_with_keys()) {
+            for options in option_sets() {
+                test_round_trip(before.clone(), after.clone(), &options);
+            }
+            assert_iter_agrees(&before, &after);
+        }
+
+        #[test]
+        fn random_diffs_round_trip(before in arb_value(), after in arb_value()) {
+            for options in option_sets() {
+                test_round_trip(before.clone(), after.clone(), &options);
+            }
+        }
+
+        #[test]
+        fn random_diff_iters_agree_with_diff(before in arb_value(), after in arb_value()) {
+            assert_iter_agrees(&before, &after);
         }
     }
 
@@ -181,6 +967,21 @@ mod test {
         }
     }
 
+    #[test]
+    fn numerically_equal_values_should_not_generate_patches() {
+        // as if `before` had been through a system that stores every number as a float
+        let before = json!({"a": 1, "b": [1, 2, {"c": 3}], "d": 4});
+        let after =
+            serde_json::from_str(r#"{"a": 1.0, "b": [1.0, 2.0, {"c": 3.0}], "d": 4.5}"#).unwrap();
+        assert_eq!(
+            diff(&before, &after),
+            vec![Patch::Replace {
+                path: Path::new("/d"),
+                value: json!(4.5)
+            }]
+        );
+    }
+
     #[test]
     fn simple_examples() {
         let add_hello = Patch::Add {
@@ -226,6 +1027,83 @@ mod test {
         );
     }
 
+    // long enough that, with the `rayon` feature, the LCS and the changed elements are diffed in parallel
+    #[test]
+    fn large_array_diffs_round_trip() {
+        let before = (0..800)
+            .map(|i| json!({"id": i % 11, "value": i}))
+            .collect::<Value>();
+        let after = (0..800)
+            .filter(|i| i % 5 != 0)
+            .map(|i| json!({"id": i % 11, "value": i * (i % 3)}))
+            .collect::<Value>();
+        let patches = diff(&before, &after);
+        assert_iter_agrees(&before, &after);
+        assert_eq!(apply(before, patches), Ok(after));
+    }
+
+    // far too many edits for the LCS to be worth searching for, so the elements are diffed by index
+    #[test]
+    fn mostly_changed_large_arrays_should_be_diffed_by_index() {
+        let before = (0..5000)
+            .map(|i| json!({"id": i, "value": 0}))
+            .collect::<Value>();
+        let after = (0..5000)
+            .map(|i| json!({"id": i, "value": 1}))
+            .chain(once(json!({"id": "new"})))
+            .collect::<Value>();
+        let by_index = DiffOptions::default().array_strategy(ArrayStrategy::Index);
+        let patches = diff(&before, &after);
+        assert_eq!(patches, diff_with(&before, &after, &by_index));
+        assert_eq!(patches.len(), 5001);
+        assert_iter_agrees(&before, &after);
+        assert_eq!(apply(before, patches), Ok(after));
+    }
+
+    // with `preserve_order`, these maps iterate in the reverse order of their keys
+    #[test]
+    fn patches_should_be_in_canonical_order() {
+        let reversed = |members: &[(&str, Value)]| {
+            Value::Object(
+                members
+                    .iter()
+                    .rev()
+                    .map(|(key, value)| (key.to_string(), value.clone()))
+                    .collect(),
+            )
+        };
+        let before = reversed(&[
+            ("a", json!(1)),
+            ("b", reversed(&[("x", json!(1)), ("y", json!(1))])),
+            ("c", json!(1)),
+            ("d", json!(1)),
+        ]);
+        let after = reversed(&[
+            ("b", reversed(&[("x", json!(2)), ("y", json!(2))])),
+            ("d", json!(2)),
+            ("e", json!(1)),
+            ("f", json!(1)),
+        ]);
+        let patch = |op: &str, path: &str| {
+            let mut patch = json!({"op": op, "path": path});
+            if op != "remove" {
+                patch["value"] = after.pointer(path).unwrap().clone();
+            }
+            serde_json::from_value::<Patch>(patch).unwrap()
+        };
+        let expected = vec![
+            patch("remove", "/a"),
+            patch("remove", "/c"),
+            patch("add", "/e"),
+            patch("add", "/f"),
+            patch("replace", "/b/x"),
+            patch("replace", "/b/y"),
+            patch("replace", "/d"),
+        ];
+        assert_eq!(diff(&before, &after), expected);
+        assert_iter_agrees(&before, &after);
+    }
+
     #[test]
     fn should_replace_where_appropriate() {
         let before = &json!({"hello": "world"});
@@ -0,0 +1,171 @@
+// move and copy detection runs over the patches produced by the rest of the diff
+// a `Remove` and an `Add` of the same value become a single `Move`, placed where the `Add` was
+// along with the test guard of the `Remove`, if there is one, so the guard still runs before the value is moved
+// an `Add` of a value that also exists, unchanged, elsewhere in the document becomes a `Copy`
+//
+// this is only correct when the paths involved can't shift while the patches are applied, so:
+//  - a moved value must be removed from an object member, not an array element, at every level
+//  - a copied value must come from an object member that is the same in `before` and `after`, at every level
+
+use std::{collections::HashMap, iter::once};
+
+use serde_json::Value;
+
+use crate::{json_eq, Patch, Path};
+
+use super::{hash::Hashes, DiffOptions};
+
+pub(super) fn detect_moves_and_copies(
+    before: &Value,
+    after: &Value,
+    patches: Vec<Patch>,
+    options: &DiffOptions,
+    hashes: &Hashes,
+) -> Vec<Patch> {
+    let mut removed = HashMap::<u64, Vec<usize>>::new();
+    if options.detect_moves {
+        for (index, patch) in patches.iter().enumerate() {
+            if let Patch::Remove { path } = patch {
+                if let Some(value) = object_member(before, path) {
+                    removed.entry(hashes.of(value)).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    let mut unchanged = HashMap::<u64, Vec<Path>>::new();
+    if options.detect_copies {
+        index_unchanged(before, after, &mut Path::root(), hashes, &mut unchanged);
+    }
+
+    // the `Remove` patches that have become part of a `Move`, along with their guards
+    let mut moved = vec![false; patches.len()];
+    // the `Add` patches that have become a `Move` or a `Copy`
+    let mut replacements = vec![None; patches.len()];
+    // the guards that now precede a `Move` instead of a `Remove`
+    let mut guards = vec![None; patches.len()];
+
+    for (index, patch) in patches.iter().enumerate() {
+        let (path, value) = match patch {
+            Patch::Add { path, value } => (path, value),
+            _ => continue,
+        };
+        let hash = hashes.of(value);
+
+        let move_from = removed.get(&hash).and_then(|candidates| {
+            candidates.iter().copied().find(|candidate| {
+                let from = match &patches[*candidate] {
+                    Patch::Remove { path } => path,
+                    _ => return false,
+                };
+                !moved[*candidate]
+                    && !path.starts_with(from)
+                    && !from.starts_with(path)
+                    && matches!(object_member(before, from), Some(old) if json_eq(old, value))
+            })
+        });
+        if let Some(candidate) = move_from {
+            moved[candidate] = true;
+            if let Patch::Remove { path: from } = &patches[candidate] {
+                if let Some(guard @ Patch::Test { path: tested, .. }) =
+                    candidate.checked_sub(1).map(|guard| &patches[guard])
+                {
+                    if tested == from {
+                        moved[candidate - 1] = true;
+                        guards[index] = Some(guard.clone());
+                    }
+                }
+                replacements[index] = Some(Patch::Move {
+                    from: from.clone(),
+                    path: path.clone(),
+                });
+            }
+            continue;
+        }
+
+        if !is_container(value) {
+            continue;
+        }
+        let copy_from = unchanged.get(&hash).and_then(|candidates| {
+            candidates.iter().find(
+                |from| matches!(object_member(before, from), Some(old) if json_eq(old, value)),
+            )
+        });
+        if let Some(from) = copy_from {
+            replacements[index] = Some(Patch::Copy {
+                from: from.clone(),
+                path: path.clone(),
+            });
+        }
+    }
+
+    patches
+        .into_iter()
+        .zip(replacements)
+        .zip(guards)
+        .zip(moved)
+        .filter(|(_, moved)| !moved)
+        .flat_map(|(((patch, replacement), guard), _)| {
+            guard.into_iter().chain(once(replacement.unwrap_or(patch)))
+        })
+        .collect()
+}
+
+/// The value at `path`, if every token of `path` refers to a member of an object
+fn object_member<'a>(root: &'a Value, path: &Path) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(root, |value, token| value.as_object()?.get(token))
+}
+
+// scalars are as cheap to add as they are to copy
+fn is_container(value: &Value) -> bool {
+    match value {
+        Value::Array(vec) => !vec.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Record the paths of containers that are reachable through object members, and are the same in `before` and `after`
+fn index_unchanged(
+    before: &Value,
+    after: &Value,
+    path: &mut Path,
+    hashes: &Hashes,
+    index: &mut HashMap<u64, Vec<Path>>,
+) {
+    if hashes.eq(before, after) {
+        index_subtree(before, path, hashes, index);
+        return;
+    }
+    if let (Value::Object(before), Value::Object(after)) = (before, after) {
+        for (key, value) in before {
+            if let Some(other) = after.get(key) {
+                path.push(key.as_str());
+                index_unchanged(value, other, path, hashes, index);
+                path.pop();
+            }
+        }
+    }
+}
+
+fn index_subtree(
+    value: &Value,
+    path: &mut Path,
+    hashes: &Hashes,
+    index: &mut HashMap<u64, Vec<Path>>,
+) {
+    if let Value::Object(map) = value {
+        for (key, value) in map {
+            path.push(key.as_str());
+            index_subtree(value, path, hashes, index);
+            path.pop();
+        }
+    }
+    if is_container(value) {
+        index
+            .entry(hashes.of(value))
+            .or_default()
+            .push(path.clone());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::Path;
+
+use super::{ArrayKey, CostModel};
+
+/// Options controlling how diffs are computed
+///
+/// The default options are those used by [diff](crate::diff)
+/// ```rust
+/// # use jatch::{diff_with, ArrayStrategy, DiffOptions, Patch, Path};
+/// # use serde_json::json;
+/// let before = json!([1, 2, 3]);
+/// let after = json!([0, 1, 2, 3]);
+/// let insert = vec![Patch::Add {
+///   path: Path::new("/0"),
+///   value: json!(0),
+/// }];
+/// assert_eq!(diff_with(&before, &after, &DiffOptions::default()), insert);
+///
+/// let by_index = DiffOptions::default().array_strategy(ArrayStrategy::Index);
+/// assert_eq!(diff_with(&before, &after, &by_index).len(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    pub(crate) array_strategy: ArrayStrategy,
+    pub(crate) array_keys: Vec<(Path, ArrayKey)>,
+    pub(crate) cost_model: Option<CostModel>,
+    pub(crate) detect_moves: bool,
+    pub(crate) detect_copies: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) ignored: Vec<Path>,
+    pub(crate) prefer_replace: bool,
+    pub(crate) test_guards: bool,
+    pub(crate) version_guard: Option<Path>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            array_strategy: ArrayStrategy::Lcs,
+            array_keys: vec![],
+            cost_model: None,
+            detect_moves: false,
+            detect_copies: false,
+            max_depth: None,
+            ignored: vec![],
+            prefer_replace: true,
+            test_guards: false,
+            version_guard: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Set how elements of arrays are matched up
+    pub fn array_strategy(mut self, array_strategy: ArrayStrategy) -> Self {
+        self.array_strategy = array_strategy;
+        self
+    }
+
+    /// Match up the elements of the array at `path` by their identity, rather than using the [ArrayStrategy]
+    ///
+    /// `path` is the location of the array in the document after the patches are applied.
+    /// Moved elements produce [Patch::Move](crate::Patch::Move) operations, and matched elements are diffed against each other
+    /// ```rust
+    /// # use jatch::{diff_with, ArrayKey, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"users": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}]});
+    /// let after = json!({"users": [{"id": 2, "name": "b"}, {"id": 1, "name": "c"}]});
+    /// let options = DiffOptions::default().array_key(Path::new("/users"), ArrayKey::field("id"));
+    /// assert_eq!(diff_with(&before, &after, &options), vec![
+    ///   Patch::Move {
+    ///     from: Path::new("/users/0"),
+    ///     path: Path::new("/users/1"),
+    ///   },
+    ///   Patch::Replace {
+    ///     path: Path::new("/users/1/name"),
+    ///     value: json!("c"),
+    ///   },
+    /// ]);
+    /// ```
+    pub fn array_key(mut self, path: Path, key: ArrayKey) -> Self {
+        self.array_keys.retain(|(existing, _)| *existing != path);
+        self.array_keys.push((path, key));
+        self
+    }
+
+    /// Replace a changed object or array as a whole when that is cheaper, according to `cost_model`, than changing its contents
+    ///
+    /// This is off by default, including for [diff](crate::diff), so changes are expressed as precisely as possible,
+    /// however many operations that takes.
+    /// The cost of the patches for a subtree is estimated once for each of its parents, so this is slower for deeply nested documents
+    /// ```rust
+    /// # use jatch::{diff_with, CostModel, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"user": {"name": "a", "email": "a@example.com", "age": 1}});
+    /// let after = json!({"user": {"name": "b", "email": "b@example.com", "age": 2}});
+    /// assert_eq!(diff_with(&before, &after, &DiffOptions::default()).len(), 3);
+    ///
+    /// let options = DiffOptions::default().cost_model(CostModel::Size);
+    /// assert_eq!(diff_with(&before, &after, &options), vec![Patch::Replace {
+    ///   path: Path::new("/user"),
+    ///   value: json!({"name": "b", "email": "b@example.com", "age": 2}),
+    /// }]);
+    /// ```
+    pub fn cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = Some(cost_model);
+        self
+    }
+
+    /// Turn the removal of a value followed by an addition of the same value elsewhere into a [Patch::Move](crate::Patch::Move)
+    ///
+    /// Only values removed from object members are moved, since the indices of array elements shift as patches are applied
+    /// ```rust
+    /// # use jatch::{diff_with, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"config": {"old": {"big": [1, 2, 3]}}});
+    /// let after = json!({"config": {"new": {"big": [1, 2, 3]}}});
+    /// let options = DiffOptions::default().detect_moves(true);
+    /// assert_eq!(diff_with(&before, &after, &options), vec![Patch::Move {
+    ///   from: Path::new("/config/old"),
+    ///   path: Path::new("/config/new"),
+    /// }]);
+    /// ```
+    pub fn detect_moves(mut self, detect_moves: bool) -> Self {
+        self.detect_moves = detect_moves;
+        self
+    }
+
+    /// Turn the addition of an object or array that also exists elsewhere, unchanged, into a [Patch::Copy](crate::Patch::Copy)
+    ///
+    /// Values are only copied from object members that are the same before and after the patches are applied.
+    /// Scalars are always added, since they are as cheap to add as they are to copy
+    /// ```rust
+    /// # use jatch::{diff_with, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"a": {"big": [1, 2, 3]}});
+    /// let after = json!({"a": {"big": [1, 2, 3]}, "b": [1, 2, 3]});
+    /// let options = DiffOptions::default().detect_copies(true);
+    /// assert_eq!(diff_with(&before, &after, &options), vec![Patch::Copy {
+    ///   from: Path::new("/a/big"),
+    ///   path: Path::new("/b"),
+    /// }]);
+    /// ```
+    pub fn detect_copies(mut self, detect_copies: bool) -> Self {
+        self.detect_copies = detect_copies;
+        self
+    }
+
+    /// Replace values that are `max_depth` or more tokens deep as a whole, rather than diffing their contents
+    ///
+    /// A `max_depth` of 0 replaces the whole document if anything changed.
+    /// Values that contain an [ignored](DiffOptions::ignore) path are still diffed, so that the ignored values aren't overwritten
+    /// ```rust
+    /// # use jatch::{diff_with, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"a": {"b": 1, "c": 2}});
+    /// let after = json!({"a": {"b": 1, "c": 3}});
+    /// let options = DiffOptions::default().max_depth(1);
+    /// assert_eq!(diff_with(&before, &after, &options), vec![Patch::Replace {
+    ///   path: Path::new("/a"),
+    ///   value: json!({"b": 1, "c": 3}),
+    /// }]);
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Ignore any changes at or below `path`, which is a location in the document after the patches are applied
+    ///
+    /// Ignored object members are never added, removed or changed.
+    /// Ignored array elements can still be added or removed, since skipping that would shift the following elements,
+    /// and ignored values are carried along when one of their parents is added, removed or replaced as a whole
+    /// ```rust
+    /// # use jatch::{diff_with, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"data": 1, "updated_at": 100});
+    /// let after = json!({"data": 2, "updated_at": 200});
+    /// let options = DiffOptions::default().ignore(Path::new("/updated_at"));
+    /// assert_eq!(diff_with(&before, &after, &options), vec![Patch::Replace {
+    ///   path: Path::new("/data"),
+    ///   value: json!(2),
+    /// }]);
+    /// ```
+    pub fn ignore(mut self, path: Path) -> Self {
+        self.ignored.push(path);
+        self
+    }
+
+    /// When an array element is removed and another is added at the same index, diff them against each other
+    ///
+    /// This is enabled by default. When disabled, the old element is removed and the new element is added, which can
+    /// be clearer when array elements are replaced rather than edited. This only affects [ArrayStrategy::Lcs]
+    pub fn prefer_replace(mut self, prefer_replace: bool) -> Self {
+        self.prefer_replace = prefer_replace;
+        self
+    }
+
+    /// Precede every [Patch::Remove](crate::Patch::Remove) and [Patch::Replace](crate::Patch::Replace) with a [Patch::Test](crate::Patch::Test) of the value being overwritten
+    ///
+    /// The patches then fail to apply to a document that has changed since it was diffed, rather than overwriting the changes
+    /// ```rust
+    /// # use jatch::{apply, diff_with, DiffOptions, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"a": 1});
+    /// let after = json!({"a": 2});
+    /// let options = DiffOptions::default().test_guards(true);
+    /// let patches = diff_with(&before, &after, &options);
+    /// assert_eq!(patches[0], Patch::Test {
+    ///   path: Path::new("/a"),
+    ///   value: json!(1),
+    /// });
+    /// assert!(apply(json!({"a": 3}), patches).is_err());
+    /// ```
+    pub fn test_guards(mut self, test_guards: bool) -> Self {
+        self.test_guards = test_guards;
+        self
+    }
+
+    /// Start the patches with a single [Patch::Test](crate::Patch::Test) of the value at `path`, such as a version number
+    ///
+    /// This is cheaper than [DiffOptions::test_guards] when every change to the document also changes the value at `path`.
+    /// If there is no value at `path` before the patches are applied, the closest parent of `path` that exists is tested
+    /// instead, so the patches only apply while `path` is still missing
+    /// ```rust
+    /// # use jatch::{apply, diff_with, DiffOptions, Error, Patch, Path};
+    /// # use serde_json::json;
+    /// let before = json!({"version": 1, "name": "a"});
+    /// let after = json!({"version": 2, "name": "b"});
+    /// let options = DiffOptions::default().version_guard(Path::new("/version"));
+    /// let patches = diff_with(&before, &after, &options);
+    /// assert_eq!(patches[0], Patch::Test {
+    ///   path: Path::new("/version"),
+    ///   value: json!(1),
+    /// });
+    ///
+    /// // someone else changed the document in the meantime
+    /// let concurrent = json!({"version": 2, "name": "c"});
+    /// let error = apply(concurrent, patches).unwrap_err();
+    /// assert!(matches!(error.root_cause(), Error::FailedTest { .. }));
+    /// ```
+    pub fn version_guard(mut self, path: Path) -> Self {
+        self.version_guard = Some(path);
+        self
+    }
+
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        self.ignored.iter().any(|ignored| path.starts_with(ignored))
+    }
+
+    /// Whether any ignored path is strictly below `path`
+    pub(crate) fn contains_ignored(&self, path: &Path) -> bool {
+        self.ignored
+            .iter()
+            .any(|ignored| ignored.starts_with(path) && ignored != path)
+    }
+
+    pub(crate) fn is_too_deep(&self, path: &Path) -> bool {
+        matches!(self.max_depth, Some(max_depth) if path.len() >= max_depth)
+    }
+
+    pub(crate) fn array_key_for(&self, path: &Path) -> Option<&ArrayKey> {
+        self.array_keys
+            .iter()
+            .find(|(existing, _)| existing == path)
+            .map(|(_, key)| key)
+    }
+}
+
+/// How [diff_with](crate::diff_with) matches up the elements of two arrays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayStrategy {
+    /// Find the longest common subsequence of the arrays, and add and remove the elements around it
+    ///
+    /// This produces minimal patches when elements are inserted or removed, at the cost of `O((N + M) * D)` comparisons,
+    /// where `D` is the number of differing elements. Large arrays that differ in so many elements that this would take
+    /// too long are diffed like [ArrayStrategy::Index] instead, so diffing is never quadratic in the length of the arrays
+    Lcs,
+    /// Compare elements at the same index, and add or remove elements at the end
+    ///
+    /// This is fast, but inserting an element at the start of an array produces a patch for every following element
+    Index,
+}
@@ -0,0 +1,89 @@
+// the patches for the contents of a container are collected as a list of steps, rather than diffing each child as it is found
+// that way, with the `rayon` feature, the children can be diffed in parallel, while the patches are still emitted in order
+
+use serde_json::Value;
+
+use crate::{Patch, Path};
+
+use super::{cost::Costed, diff_with_root, hash::Hashes, DiffOptions};
+
+pub(super) struct Steps<'v> {
+    steps: Vec<Step<'v>>,
+    /// The number of `Step::Diff`s in `steps`
+    diffs: usize,
+}
+
+enum Step<'v> {
+    /// Patches that are already known
+    Patches(Vec<Patch>),
+    /// Diff `before` against `after`, which are both at `path`
+    Diff {
+        before: &'v Value,
+        after: &'v Value,
+        path: Path,
+    },
+}
+
+impl<'v> Steps<'v> {
+    pub(super) fn new() -> Self {
+        Self {
+            steps: vec![],
+            diffs: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, patch: Patch) {
+        match self.steps.last_mut() {
+            Some(Step::Patches(patches)) => patches.push(patch),
+            _ => self.steps.push(Step::Patches(vec![patch])),
+        }
+    }
+
+    pub(super) fn diff(&mut self, before: &'v Value, after: &'v Value, path: Path) {
+        self.steps.push(Step::Diff {
+            before,
+            after,
+            path,
+        });
+        self.diffs += 1;
+    }
+
+    /// Diff every child, returning all the patches in the order of the steps
+    pub(super) fn run(self, options: &DiffOptions, hashes: &Hashes) -> Costed {
+        let run = |step: Step<'v>| match step {
+            Step::Patches(patches) => Costed::new(patches, options),
+            Step::Diff {
+                before,
+                after,
+                path,
+            } => diff_with_root(before, after, path, options, hashes),
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            // a single child is as fast to diff on this thread
+            if self.diffs > 1 {
+                let patches = self.steps.into_par_iter().map(run).collect::<Vec<_>>();
+                return patches.into_iter().fold(Costed::default(), combine);
+            }
+        }
+        self.steps
+            .into_iter()
+            .map(run)
+            .fold(Costed::default(), combine)
+    }
+}
+
+fn combine(mut patches: Costed, more: Costed) -> Costed {
+    patches.append(more);
+    patches
+}
+
+impl Extend<Patch> for Steps<'_> {
+    fn extend<T: IntoIterator<Item = Patch>>(&mut self, patches: T) {
+        for patch in patches {
+            self.push(patch);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::hash::{Hash, Hasher};
+
+use serde_json::{Map, Number, Value};
+
+/// Compare two JSON values for equality, as defined by [RFC 6902 section 4.6](https://datatracker.ietf.org/doc/html/rfc6902#section-4.6)
+///
+/// This differs from the `PartialEq` implementation of [Value] in that numbers are compared by their numeric value,
+/// so `1`, `1.0` and `10e-1` are all equal. With serde_json's `arbitrary_precision` feature, numbers are compared exactly,
+/// without rounding them to an `f64`
+///
+/// ```rust
+/// # use jatch::json_eq;
+/// # use serde_json::json;
+/// assert!(json_eq(&json!({"a": [1, 2.5]}), &json!({"a": [1.0, 2.5]})));
+/// assert!(!json_eq(&json!([1, 2]), &json!([2, 1])));
+/// ```
+pub fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => number_eq(a, b),
+        (Value::Array(a), Value::Array(b)) => slice_eq(a, b),
+        (Value::Object(a), Value::Object(b)) => map_eq(a, b),
+        (a, b) => a == b,
+    }
+}
+
+fn slice_eq(a: &[Value], b: &[Value]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+}
+
+fn map_eq(a: &Map<String, Value>, b: &Map<String, Value>) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, a)| matches!(b.get(key), Some(b) if json_eq(a, b)))
+}
+
+fn number_eq(a: &Number, b: &Number) -> bool {
+    if a == b {
+        return true;
+    }
+    if let (Some(a), Some(b)) = (as_integer(a), as_integer(b)) {
+        return a == b;
+    }
+    match (Decimal::parse(a), Decimal::parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Hash `n` such that numbers which are equal according to [json_eq] have equal hashes
+pub(crate) fn hash_number<H: Hasher>(n: &Number, hasher: &mut H) {
+    match Decimal::parse(n) {
+        Some(decimal) => decimal.hash(hasher),
+        None => n.to_string().hash(hasher),
+    }
+}
+
+fn as_integer(n: &Number) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+/// A number in the form `0.digits * 10^exponent`, with no leading or trailing zeros in `digits`
+///
+/// Every number has exactly one such form, so it can be compared and hashed directly
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Parse the textual form of `n`, which is exact even with `arbitrary_precision`
+    ///
+    /// Without `arbitrary_precision`, a float is printed as the shortest string that parses back to the same `f64`,
+    /// so equal floats have equal forms. Returns `None` if the exponent doesn't fit in an `i64`
+    fn parse(n: &Number) -> Option<Self> {
+        let text = n.to_string();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text.as_str()),
+        };
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(e) => (&text[..e], text[e + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (integer, fraction) = match mantissa.find('.') {
+            Some(dot) => (&mantissa[..dot], &mantissa[dot + 1..]),
+            None => (mantissa, ""),
+        };
+
+        let mut exponent = exponent.checked_add(integer.len() as i64)?;
+        let mut digits = integer.chars().chain(fraction.chars()).collect::<String>();
+        let leading_zeros = digits.len() - digits.trim_start_matches('0').len();
+        exponent = exponent.checked_sub(leading_zeros as i64)?;
+        digits = digits.trim_matches('0').to_string();
+
+        // zero has no digits, and is equal to negative zero
+        if digits.is_empty() {
+            return Some(Decimal {
+                negative: false,
+                digits,
+                exponent: 0,
+            });
+        }
+        Some(Decimal {
+            negative,
+            digits,
+            exponent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        serde_json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn numbers_should_compare_by_value() {
+        let equal = [
+            ("1", "1.0"),
+            ("1", "10e-1"),
+            ("100", "1e2"),
+            ("100", "1E+2"),
+            ("-2.5", "-25e-1"),
+            ("0", "-0.0"),
+            ("0.001", "1e-3"),
+            ("9007199254740992", "9007199254740992.0"),
+        ];
+        for (a, b) in equal.iter() {
+            assert!(json_eq(&parse(a), &parse(b)), "{} and {}", a, b);
+        }
+
+        let different = [("1", "-1"), ("1", "1.5"), ("10", "1"), ("0.1", "0.01")];
+        for (a, b) in different.iter() {
+            assert!(!json_eq(&parse(a), &parse(b)), "{} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn equal_numbers_should_have_equal_hashes() {
+        fn hash(s: &str) -> u64 {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            match parse(s) {
+                Value::Number(n) => hash_number(&n, &mut hasher),
+                _ => unreachable!(),
+            }
+            hasher.finish()
+        }
+        assert_eq!(hash("1"), hash("1.0"));
+        assert_eq!(hash("-0.0"), hash("0"));
+        assert_eq!(hash("1.5e3"), hash("1500"));
+        assert_ne!(hash("1"), hash("2"));
+    }
+
+    #[test]
+    fn containers_should_compare_deeply() {
+        assert!(json_eq(
+            &json!({"a": [1, {"b": 2}], "c": null}),
+            &parse(r#"{"c": null, "a": [1.0, {"b": 2.0}]}"#)
+        ));
+        assert!(!json_eq(&json!({"a": 1}), &json!({"a": 1, "b": 1})));
+        assert!(!json_eq(&json!({"a": 1}), &json!({"b": 1})));
+        assert!(!json_eq(&json!([1]), &json!([1, 1])));
+        assert!(!json_eq(&json!("1"), &json!(1)));
+        assert!(!json_eq(&json!(true), &json!(1)));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn arbitrary_precision_numbers_should_compare_exactly() {
+        assert!(json_eq(&parse("100000000000000000000000"), &parse("1e23")));
+        assert!(!json_eq(&parse("100000000000000000000001"), &parse("1e23")));
+        assert!(!json_eq(&parse("0.30000000000000000001"), &parse("0.3")));
+    }
+}
@@ -1,14 +1,63 @@
 use std::fmt::Display;
 
+use serde_json::Value;
+
+use crate::{patch::OpKind, Path};
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(format!("{:?}", self).as_ref())
+        match self {
+            Error::InvalidPath { path, position } => {
+                if *position == 0 && !path.starts_with('/') {
+                    write!(f, "invalid JSON pointer \"{}\": must start with '/'", path)
+                } else {
+                    write!(
+                        f,
+                        "invalid JSON pointer \"{}\": '~' at position {} must be followed by '0' or '1'",
+                        path, position
+                    )
+                }
+            }
+            Error::PathDoesntExist { path, prefix, kind } => write!(
+                f,
+                "path \"{}\" doesn't exist: {} at \"{}\"",
+                path.to_escaped(),
+                kind,
+                prefix.to_escaped()
+            ),
+            Error::FailedTest {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "test failed at \"{}\": expected {}, found {}",
+                path.to_escaped(),
+                expected,
+                actual
+            ),
+            Error::MoveIntoChild { from, path } => write!(
+                f,
+                "cannot move \"{}\" into its own child \"{}\"",
+                from.to_escaped(),
+                path.to_escaped()
+            ),
+            Error::NullInMergePatch { path } => {
+                write!(f, "merge patch can't set \"{}\" to null", path.to_escaped())
+            }
+            Error::Operation { index, op, error } => {
+                write!(f, "operation {} ({}) failed: {}", index, op, error)
+            }
+        }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        match self {
+            Error::Operation { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
     }
 
     fn description(&self) -> &str {
@@ -22,11 +71,196 @@ impl std::error::Error for Error {
 
 /// An error encountered while performing JSON Patch operations
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// The provided string was not a valid JSON Pointer as defined in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
-    InvalidPath(String),
+    InvalidPath {
+        /// The string that failed to parse
+        path: String,
+        /// The byte offset into `path` at which the error was found
+        position: usize,
+    },
     /// The path given didn't refer to a valid location in the document
-    PathDoesntExist,
+    PathDoesntExist {
+        /// The full path that was being resolved
+        path: Path,
+        /// The prefix of `path` that failed to resolve, ending with the offending token
+        prefix: Path,
+        /// Why `prefix` couldn't be resolved
+        kind: PathErrorKind,
+    },
     /// A JSON Patch 'test' operation failed
-    FailedTest,
+    FailedTest {
+        /// The path that was tested
+        path: Path,
+        /// The value the test expected
+        expected: Box<Value>,
+        /// The value actually found at `path`
+        actual: Box<Value>,
+    },
+    /// A JSON Patch 'move' operation tried to move a location into one of its own children
+    MoveIntoChild {
+        /// The location being moved
+        from: Path,
+        /// The destination, which is inside `from`
+        path: Path,
+    },
+    /// A JSON Merge Patch would need to set an object member to `null`, which it can't express
+    NullInMergePatch {
+        /// The object member that would be set to `null`
+        path: Path,
+    },
+    /// An operation in a collection of patches failed
+    Operation {
+        /// The index of the failing operation
+        index: usize,
+        /// The kind of the failing operation
+        op: OpKind,
+        /// The reason the operation failed
+        error: Box<Error>,
+    },
+}
+
+/// The reason a path couldn't be resolved against a document
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum PathErrorKind {
+    /// An object didn't contain the key
+    KeyNotFound,
+    /// An array index was past the end of the array
+    IndexOutOfBounds {
+        /// The index that was requested
+        index: usize,
+        /// The length of the array
+        len: usize,
+    },
+    /// The token was used to index into a value that isn't an object or array
+    NotAContainer,
+    /// The token was used to index into an array, but wasn't a valid array index
+    InvalidIndex,
+    /// The token was `-`, which refers to the nonexistent element after the end of an array, so it can only be added to
+    EndOfArray,
+}
+
+impl Display for PathErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathErrorKind::KeyNotFound => f.write_str("key not found"),
+            PathErrorKind::IndexOutOfBounds { index, len } => write!(
+                f,
+                "index {} is out of bounds for array of length {}",
+                index, len
+            ),
+            PathErrorKind::NotAContainer => f.write_str("parent is not an object or array"),
+            PathErrorKind::InvalidIndex => f.write_str("invalid array index"),
+            PathErrorKind::EndOfArray => {
+                f.write_str("'-' refers to the end of the array, so it can only be added to")
+            }
+        }
+    }
+}
+
+impl Error {
+    /// The error that caused this error, skipping over any [Error::Operation] context
+    /// ```rust
+    /// # use jatch::{apply, Error, Patch, Path};
+    /// # use serde_json::json;
+    /// let patch = Patch::Remove { path: Path::new("/missing") };
+    /// let error = apply(json!({}), vec![patch]).unwrap_err();
+    /// assert!(matches!(error, Error::Operation { index: 0, .. }));
+    /// assert!(matches!(error.root_cause(), Error::PathDoesntExist { .. }));
+    /// ```
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::Operation { error, .. } => error.root_cause(),
+            error => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_display_human_readable_messages() {
+        let cases = vec![
+            (
+                Error::InvalidPath {
+                    path: "foo".to_string(),
+                    position: 0,
+                },
+                "invalid JSON pointer \"foo\": must start with '/'",
+            ),
+            (
+                Error::InvalidPath {
+                    path: "/a~2".to_string(),
+                    position: 2,
+                },
+                "invalid JSON pointer \"/a~2\": '~' at position 2 must be followed by '0' or '1'",
+            ),
+            (
+                Error::PathDoesntExist {
+                    path: Path::new("/a/b/c"),
+                    prefix: Path::new("/a/b"),
+                    kind: PathErrorKind::KeyNotFound,
+                },
+                "path \"/a/b/c\" doesn't exist: key not found at \"/a/b\"",
+            ),
+            (
+                Error::PathDoesntExist {
+                    path: Path::new("/a/5"),
+                    prefix: Path::new("/a/5"),
+                    kind: PathErrorKind::IndexOutOfBounds { index: 5, len: 2 },
+                },
+                "path \"/a/5\" doesn't exist: index 5 is out of bounds for array of length 2 at \"/a/5\"",
+            ),
+            (
+                Error::FailedTest {
+                    path: Path::new("/a"),
+                    expected: Box::new(json!("foo")),
+                    actual: Box::new(json!({"b": 1})),
+                },
+                "test failed at \"/a\": expected \"foo\", found {\"b\":1}",
+            ),
+            (
+                Error::MoveIntoChild {
+                    from: Path::new("/a"),
+                    path: Path::new("/a/b"),
+                },
+                "cannot move \"/a\" into its own child \"/a/b\"",
+            ),
+            (
+                Error::NullInMergePatch {
+                    path: Path::new("/a/b"),
+                },
+                "merge patch can't set \"/a/b\" to null",
+            ),
+            (
+                Error::Operation {
+                    index: 3,
+                    op: OpKind::Remove,
+                    error: Box::new(Error::PathDoesntExist {
+                        path: Path::new("/a"),
+                        prefix: Path::new("/a"),
+                        kind: PathErrorKind::NotAContainer,
+                    }),
+                },
+                "operation 3 (remove) failed: path \"/a\" doesn't exist: parent is not an object or array at \"/a\"",
+            ),
+            (
+                Error::PathDoesntExist {
+                    path: Path::new("/a/-"),
+                    prefix: Path::new("/a/-"),
+                    kind: PathErrorKind::EndOfArray,
+                },
+                "path \"/a/-\" doesn't exist: '-' refers to the end of the array, so it can only be added to at \"/a/-\"",
+            ),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
 }
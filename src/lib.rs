@@ -47,15 +47,30 @@
 //!     }
 //! );
 //! ```
+//!
+//! JSON Merge Patch ([RFC 7396](https://datatracker.ietf.org/doc/html/rfc7396)) is supported by the [merge_patch] module
+//!
+//! A [Path], or a [PathRef] that borrows its tokens, can also be resolved against a document directly with [get], [get_mut],
+//! [take] and [set], which follow the same rules, and return the same errors, as the patch operations
 mod diff;
+mod eq;
 mod errors;
+pub mod merge_patch;
 mod patch;
 mod path;
 
-pub use diff::diff;
-pub use errors::Error;
+pub use diff::{diff, diff_iter, diff_with, ArrayKey, ArrayStrategy, CostModel, DiffOptions};
+pub use eq::json_eq;
+pub use errors::{Error, PathErrorKind};
 pub use patch::{
-    apply::{apply, apply_single},
-    Patch,
+    apply::{
+        apply, apply_atomic, apply_atomic_with, apply_best_effort, apply_mut, apply_single,
+        apply_single_mut, apply_with, apply_with_inverse, get, get_mut, set, take, ApplyOptions,
+        OperationReport, OperationStatus,
+    },
+    OpKind, Patch, PatchRef,
 };
-pub use path::Path;
+pub use path::{Path, PathRef, Pointer, Token, Tokens};
+
+#[cfg(feature = "rayon")]
+pub use patch::apply::par_apply_atomic;
@@ -0,0 +1,259 @@
+//! JSON Merge Patch, as defined in [RFC 7396](https://datatracker.ietf.org/doc/html/rfc7396)
+//!
+//! A merge patch is a JSON document that looks like the target document, containing only the members that change.
+//! Members set to `null` are removed, and any value that isn't an object replaces the target outright:
+//! ```rust
+//! # use jatch::merge_patch;
+//! # use serde_json::json;
+//! let mut doc = json!({"title": "Hello", "author": {"name": "Jane", "email": "jane@example.com"}});
+//! merge_patch::apply(&mut doc, &json!({"title": "Goodbye", "author": {"email": null}}));
+//! assert_eq!(doc, json!({"title": "Goodbye", "author": {"name": "Jane"}}));
+//! ```
+//! Since `null` means "remove", a merge patch can't set an object member to `null`
+
+use serde_json::{Map, Value};
+
+use crate::{errors::Error, json_eq, Patch, Path};
+
+/// Apply a merge patch to `target` in place
+pub fn apply(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *target = patch.clone();
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.remove(key);
+            } else {
+                apply(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Compute a merge patch that turns `before` into `after`
+///
+/// If `after` contains object members that are `null`, they can't be represented, and applying the merge patch removes them instead.
+/// Use [from_patches] to detect this case
+/// ```rust
+/// # use jatch::merge_patch;
+/// # use serde_json::json;
+/// let before = json!({"a": 1, "b": {"c": 2, "d": 3}});
+/// let after = json!({"a": 1, "b": {"c": 2, "e": 4}});
+/// let patch = merge_patch::diff(&before, &after);
+/// assert_eq!(patch, json!({"b": {"d": null, "e": 4}}));
+/// ```
+pub fn diff(before: &Value, after: &Value) -> Value {
+    diff_at(before, after, &mut Path::root(), &mut vec![])
+}
+
+/// Builds the merge patch, recording the paths of any `null` members of `after` that can't be represented in `nulls`
+fn diff_at(before: &Value, after: &Value, path: &mut Path, nulls: &mut Vec<Path>) -> Value {
+    let (before, after) = match (before, after) {
+        (Value::Object(before), Value::Object(after)) => (before, after),
+        (_, after) => {
+            find_nulls(after, path, nulls);
+            return after.clone();
+        }
+    };
+
+    let mut patch = Map::new();
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        patch.insert(key.clone(), Value::Null);
+    }
+    for (key, value) in after {
+        let old = before.get(key);
+        if matches!(old, Some(old) if json_eq(old, value)) {
+            continue;
+        }
+        path.push(key.as_str());
+        if value.is_null() {
+            nulls.push(path.clone());
+        }
+        let value = match old {
+            Some(old) => diff_at(old, value, path, nulls),
+            None => {
+                find_nulls(value, path, nulls);
+                value.clone()
+            }
+        };
+        patch.insert(key.clone(), value);
+        path.pop();
+    }
+    Value::Object(patch)
+}
+
+/// Object members of `value` that are `null` would be treated as removals, rather than being set to `null`
+fn find_nulls(value: &Value, path: &mut Path, nulls: &mut Vec<Path>) {
+    if let Value::Object(map) = value {
+        for (key, value) in map {
+            path.push(key.as_str());
+            if value.is_null() {
+                nulls.push(path.clone());
+            }
+            find_nulls(value, path, nulls);
+            path.pop();
+        }
+    }
+}
+
+/// Convert a merge patch into the equivalent JSON Patch operations for `target`
+///
+/// A merge patch only makes sense relative to the document it is applied to, since `null` members have no effect if they are missing from `target`
+/// ```rust
+/// # use jatch::{merge_patch, Patch, Path};
+/// # use serde_json::json;
+/// let target = json!({"a": 1, "b": 2});
+/// let patches = merge_patch::to_patches(&target, &json!({"a": null, "c": 3}));
+/// assert_eq!(patches, vec![
+///   Patch::Remove { path: Path::new("/a") },
+///   Patch::Add { path: Path::new("/c"), value: json!(3) },
+/// ]);
+/// ```
+pub fn to_patches(target: &Value, merge_patch: &Value) -> Vec<Patch> {
+    let mut after = target.clone();
+    apply(&mut after, merge_patch);
+    crate::diff(target, &after)
+}
+
+/// Convert JSON Patch operations into the equivalent merge patch for `target`
+///
+/// Fails if the patches can't be applied to `target`, or if the result contains an object member set to `null`,
+/// since a merge patch has no way to express that
+/// ```rust
+/// # use jatch::{merge_patch, Error, Patch, Path};
+/// # use serde_json::json;
+/// let target = json!({"a": 1});
+/// let patches = vec![Patch::Add { path: Path::new("/b"), value: json!([1, 2]) }];
+/// assert_eq!(merge_patch::from_patches(&target, patches), Ok(json!({"b": [1, 2]})));
+///
+/// let patches = vec![Patch::Replace { path: Path::new("/a"), value: json!(null) }];
+/// assert_eq!(
+///   merge_patch::from_patches(&target, patches),
+///   Err(Error::NullInMergePatch { path: Path::new("/a") }),
+/// );
+/// ```
+pub fn from_patches(
+    target: &Value,
+    patches: impl IntoIterator<Item = Patch>,
+) -> Result<Value, Error> {
+    let after = crate::apply(target.clone(), patches)?;
+    let mut nulls = vec![];
+    let patch = diff_at(target, &after, &mut Path::root(), &mut nulls);
+    match nulls.into_iter().next() {
+        Some(path) => Err(Error::NullInMergePatch { path }),
+        None => Ok(patch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // the examples from appendix A of RFC 7396
+    fn rfc_examples() -> Vec<(Value, Value, Value)> {
+        vec![
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ]
+    }
+
+    #[test]
+    fn should_apply_rfc_examples() {
+        for (target, patch, expected) in rfc_examples() {
+            let mut result = target.clone();
+            apply(&mut result, &patch);
+            assert_eq!(result, expected, "{} merged with {}", target, patch);
+        }
+    }
+
+    #[test]
+    fn diff_should_round_trip() {
+        for (target, _, expected) in rfc_examples() {
+            let mut result = target.clone();
+            apply(&mut result, &diff(&target, &expected));
+            assert_eq!(result, expected, "{} to {}", target, expected);
+        }
+    }
+
+    #[test]
+    fn should_convert_to_and_from_patches() {
+        for (target, patch, expected) in rfc_examples() {
+            let patches = to_patches(&target, &patch);
+            assert_eq!(
+                crate::apply(target.clone(), patches.clone()),
+                Ok(expected.clone())
+            );
+
+            let patch = from_patches(&target, patches).unwrap();
+            let mut result = target.clone();
+            apply(&mut result, &patch);
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn should_reject_nulls_in_added_objects() {
+        let patches = vec![Patch::Add {
+            path: Path::new("/a"),
+            value: json!({"b": {"c": null}, "d": [null]}),
+        }];
+        assert_eq!(
+            from_patches(&json!({}), patches),
+            Err(Error::NullInMergePatch {
+                path: Path::new("/a/b/c")
+            })
+        );
+    }
+}
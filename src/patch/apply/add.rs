@@ -1,51 +1,97 @@
 use serde_json::Value;
 
-use crate::{errors::Error, patch::walk::parse_array_index, Path};
-
-pub fn add(mut root: Value, value: Value, path: Path) -> Result<Value, Error> {
-    if let Some((head, tail)) = path.split_head() {
-        // perform the update in place
-        match &mut root {
-            Value::Object(ref mut map) => {
-                // if there is no more path left, we can just insert into the map
-                if tail.is_empty() {
-                    map.insert(head, value);
-                } else {
-                    // we need to go deeper, check for inner value, update if it exists, or error if it is missing
-                    if let Some(mut inner_value) = map.remove(&head) {
-                        inner_value = add(inner_value, value, tail)?;
-                        map.insert(head, inner_value);
-                    } else {
-                        return Err(Error::PathDoesntExist);
-                    }
-                }
-            }
-            Value::Array(ref mut vec) => {
-                let head_index = parse_array_index(vec, &head)?;
-                // general logic is very similar to above
-
-                // there is no more path, just insert at the right index
-                if tail.is_empty() {
-                    vec.insert(head_index, value);
-                } else {
-                    // go deeper, check for value at the index, update if it exists, err if its missing
-                    if head_index < vec.len() {
-                        let mut inner_value = vec.remove(head_index);
-                        inner_value = add(inner_value, value, tail)?;
-                        vec.insert(head_index, inner_value);
-                    } else {
-                        return Err(Error::PathDoesntExist);
-                    }
-                }
+use crate::{
+    errors::{Error, PathErrorKind},
+    patch::walk::{array_index, last_token_error, walk_to_parent_mut},
+    Path, Pointer,
+};
+
+use super::{undo::Undo, ApplyOptions};
+
+/// Add `value` at `path`, returning how to undo the change
+///
+/// Only object members and the root can be overwritten, adding into an array shifts the following elements instead
+pub fn add(
+    root: &mut Value,
+    value: Value,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Undo, Error> {
+    try_add(root, value, path, options).map_err(|failed| failed.0)
+}
+
+/// Like [add], but hands `value` back if it couldn't be added
+///
+/// The error is boxed, since an [Error] and a [Value] together are large enough to slow down the success path
+pub fn try_add(
+    root: &mut Value,
+    value: Value,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Undo, Box<(Error, Value)>> {
+    Ok(match add_value(root, value, path, options)? {
+        Added::Overwrote(old) => Undo::Overwrite {
+            path: path.clone(),
+            old,
+        },
+        Added::Inserted { index: None } => Undo::Insert { path: path.clone() },
+        // in lenient mode, an index such as `01` is stored the way strict mode would spell it
+        Added::Inserted { index: Some(index) } => Undo::Insert {
+            path: path.with_last(index.to_string()),
+        },
+    })
+}
+
+/// What [add_value] changed
+pub enum Added {
+    /// An object member or the root was overwritten, and this is its old value
+    Overwrote(Value),
+    /// A new object member was added, or an array element was inserted at `index`
+    Inserted { index: Option<usize> },
+}
+
+/// Like [try_add], but for any [Pointer], without building the path of the value that was added
+pub fn add_value(
+    root: &mut Value,
+    value: Value,
+    path: &impl Pointer,
+    options: &ApplyOptions,
+) -> Result<Added, Box<(Error, Value)>> {
+    let (parent, segment) = match walk_to_parent_mut(root, path, options) {
+        Ok(Some(parent)) => parent,
+        Err(e) => return Err(Box::new((e, value))),
+        Ok(None) => {
+            // an "add" operation to the root of the document essentially "sets" the document to the provided value
+            return Ok(Added::Overwrote(std::mem::replace(root, value)));
+        }
+    };
+
+    match parent {
+        // inserting into a map overwrites any existing value, in place
+        Value::Object(map) => Ok(match map.insert(segment.token.into_owned(), value) {
+            Some(old) => Added::Overwrote(old),
+            None => Added::Inserted { index: None },
+        }),
+        Value::Array(vec) => {
+            let index = match array_index(vec, segment.classify(), true, options) {
+                Ok(index) => index,
+                Err(kind) => return Err(Box::new((last_token_error(path, kind), value))),
+            };
+            // the index may be equal to the length of the array, which appends
+            if index > vec.len() {
+                let kind = PathErrorKind::IndexOutOfBounds {
+                    index,
+                    len: vec.len(),
+                };
+                return Err(Box::new((last_token_error(path, kind), value)));
             }
-            _ => return Err(Error::PathDoesntExist),
+            vec.insert(index, value);
+            Ok(Added::Inserted { index: Some(index) })
         }
-        // then return the instance
-        Ok(root)
-    } else {
-        // if recursed, this is never hit, since we always check that the tail path is not empty
-        // an "add" operation to the root of the document essentially "sets" the document to the provided value
-        Ok(value)
+        _ => Err(Box::new((
+            last_token_error(path, PathErrorKind::NotAContainer),
+            value,
+        ))),
     }
 }
 
@@ -55,6 +101,15 @@ mod tests {
 
     use super::*;
 
+    fn add_to(
+        mut root: Value,
+        value: Value,
+        path: &Path,
+        options: &ApplyOptions,
+    ) -> Result<Value, Error> {
+        add(&mut root, value, path, options).map(|_| root)
+    }
+
     #[test]
     fn test_add_object() {
         let root = json!({
@@ -62,11 +117,17 @@ mod tests {
             "world": 123,
         });
         let value_to_add = json!("added");
-        let path = Path::new("/new");
-        let new = add(root, value_to_add, path).unwrap();
+        let path = &Path::new("/new");
+        let new = add_to(root, value_to_add, path, &ApplyOptions::default()).unwrap();
         assert_eq!(new.get("new").unwrap(), &json!("added"));
 
-        let new_again = add(new, json!(234), Path::new("/hello")).unwrap();
+        let new_again = add_to(
+            new,
+            json!(234),
+            &Path::new("/hello"),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
         assert_eq!(new_again.get("hello").unwrap(), &json!(234));
     }
 
@@ -84,8 +145,8 @@ mod tests {
             }
         });
         let value_to_add = json!(2);
-        let path = Path::new("/a/b/c/d");
-        let new = add(root, value_to_add, path).unwrap();
+        let path = &Path::new("/a/b/c/d");
+        let new = add_to(root, value_to_add, path, &ApplyOptions::default()).unwrap();
         let deep_object = new.get("a").unwrap().get("b").unwrap().get("c").unwrap();
         assert_eq!(deep_object.get("d").unwrap(), &json!(2));
     }
@@ -94,7 +155,7 @@ mod tests {
     fn add_into_array_by_index() {
         let root = json!([1, 2, 3]);
         let value = json!(4);
-        let root = add(root, value, Path::new("/0")).unwrap();
+        let root = add_to(root, value, &Path::new("/0"), &ApplyOptions::default()).unwrap();
         assert_eq!(root, json!([4, 1, 2, 3]));
     }
 
@@ -102,10 +163,87 @@ mod tests {
     fn add_into_array_with_hyphen() {
         let root = json!([1, 2, 3]);
         let value = json!(4);
-        let root = add(root, value, Path::new("/-")).unwrap();
+        let root = add_to(root, value, &Path::new("/-"), &ApplyOptions::default()).unwrap();
         assert_eq!(root, json!([1, 2, 3, 4]));
     }
 
+    #[test]
+    fn add_into_array_out_of_bounds() {
+        let root = json!([1, 2, 3]);
+        assert_eq!(
+            add_to(
+                root.clone(),
+                json!(4),
+                &Path::new("/4"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/4"),
+                prefix: Path::new("/4"),
+                kind: PathErrorKind::IndexOutOfBounds { index: 4, len: 3 },
+            })
+        );
+        assert_eq!(
+            add_to(
+                json!([]),
+                json!(4),
+                &Path::new("/99"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/99"),
+                prefix: Path::new("/99"),
+                kind: PathErrorKind::IndexOutOfBounds { index: 99, len: 0 },
+            })
+        );
+        assert_eq!(
+            add_to(
+                root,
+                json!(4),
+                &Path::new("/18446744073709551616"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/18446744073709551616"),
+                prefix: Path::new("/18446744073709551616"),
+                kind: PathErrorKind::InvalidIndex,
+            })
+        );
+    }
+
+    #[test]
+    fn add_should_return_undo() {
+        let mut root = json!({"a": 1, "b": [1]});
+        let options = ApplyOptions::default();
+        assert_eq!(
+            add(&mut root, json!(2), &Path::new("/a"), &options),
+            Ok(Undo::Overwrite {
+                path: Path::new("/a"),
+                old: json!(1)
+            })
+        );
+        assert_eq!(
+            add(&mut root, json!(2), &Path::new("/c"), &options),
+            Ok(Undo::Insert {
+                path: Path::new("/c")
+            })
+        );
+        assert_eq!(
+            add(&mut root, json!(2), &Path::new("/b/-"), &options),
+            Ok(Undo::Insert {
+                path: Path::new("/b/1")
+            })
+        );
+        assert_eq!(
+            add(&mut root, json!(null), &Path::root(), &options),
+            Ok(Undo::Overwrite {
+                path: Path::root(),
+                old: json!({"a": 2, "b": [1, 2], "c": 2})
+            })
+        );
+        assert_eq!(root, json!(null));
+    }
+
     #[test]
     fn deep_nesting_array_object() {
         let root = json!({
@@ -120,8 +258,39 @@ mod tests {
                 }
             ]
         });
-        let new = add(root, json!(123), Path::new("/a/2/b/2/arr/-")).unwrap();
-        let deep_array = new.get("a").unwrap().get(2).unwrap().get("b").unwrap().get(2).unwrap().get("arr").unwrap();
+        let new = add_to(
+            root,
+            json!(123),
+            &Path::new("/a/2/b/2/arr/-"),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let deep_array = new
+            .get("a")
+            .unwrap()
+            .get(2)
+            .unwrap()
+            .get("b")
+            .unwrap()
+            .get(2)
+            .unwrap()
+            .get("arr")
+            .unwrap();
         assert_eq!(deep_array, &json!([1, 2, 3, 123]));
     }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn deep_add_should_preserve_key_order() {
+        let mut root = json!({"a": 1, "b": {"c": 1}, "d": 1});
+        add(
+            &mut root,
+            json!(2),
+            &Path::new("/b/e"),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let keys = root.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "b", "d"]);
+    }
 }
@@ -1,10 +1,15 @@
 use serde_json::Value;
 
-use crate::{Path, errors::Error, patch::walk::walk};
+use crate::{errors::Error, patch::walk::walk, Path};
 
-use super::add::add;
+use super::{add::add, undo::Undo, ApplyOptions};
 
-pub fn copy(root: Value, from: Path, path: Path) -> Result<Value, Error> {
-    let value = walk(&root, from)?.clone();
-    add(root, value, path)
-}
\ No newline at end of file
+pub fn copy(
+    root: &mut Value,
+    from: &Path,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Undo, Error> {
+    let value = walk(root, from, options)?.clone();
+    add(root, value, path, options)
+}
@@ -0,0 +1,170 @@
+// property tests that throw random patches at random documents
+// mostly we check that `apply` always returns, either with a value or an error, and never panics
+// the generated paths deliberately use a small set of tokens, so that they often resolve against the generated documents
+
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+
+use super::{
+    apply, apply_atomic_with, apply_mut, apply_single, apply_with, apply_with_inverse, ApplyOptions,
+};
+use crate::{Patch, Path};
+
+// tokens that are interesting as both object keys and array indices
+const TOKENS: &[&str] = &[
+    "a",
+    "b",
+    "",
+    "0",
+    "1",
+    "2",
+    "-",
+    "01",
+    "+1",
+    "-1",
+    "99",
+    "18446744073709551615",
+    "18446744073709551616",
+    "~",
+    "/",
+];
+
+fn arb_token() -> impl Strategy<Value = String> {
+    prop::sample::select(TOKENS).prop_map(String::from)
+}
+
+fn arb_path() -> impl Strategy<Value = Path> {
+    prop::collection::vec(arb_token(), 0..5).prop_map(|tokens| {
+        tokens
+            .into_iter()
+            .fold(Path::root(), |path, token| path.join(escape(&token)))
+    })
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn arb_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        "[a-z]{0,3}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((arb_token(), inner), 0..4)
+                .prop_map(|entries| Value::Object(entries.into_iter().collect::<Map<_, _>>())),
+        ]
+    })
+}
+
+fn arb_patch() -> impl Strategy<Value = Patch> {
+    prop_oneof![
+        (arb_path(), arb_value()).prop_map(|(path, value)| Patch::Add { path, value }),
+        arb_path().prop_map(|path| Patch::Remove { path }),
+        (arb_path(), arb_value()).prop_map(|(path, value)| Patch::Replace { path, value }),
+        (arb_path(), arb_path()).prop_map(|(from, path)| Patch::Copy { from, path }),
+        (arb_path(), arb_path()).prop_map(|(from, path)| Patch::Move { from, path }),
+        (arb_path(), arb_value()).prop_map(|(path, value)| Patch::Test { path, value }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn apply_single_never_panics(root in arb_value(), patch in arb_patch()) {
+        let _ = apply_single(root, patch);
+    }
+
+    #[test]
+    fn apply_never_panics(root in arb_value(), patches in prop::collection::vec(arb_patch(), 0..8)) {
+        let _ = apply(root, patches);
+    }
+
+    #[test]
+    fn apply_mut_agrees_with_apply(root in arb_value(), patches in prop::collection::vec(arb_patch(), 0..8)) {
+        let mut in_place = root.clone();
+        let result = apply_mut(&mut in_place, &patches).map(|_| in_place);
+        prop_assert_eq!(result, apply(root, patches));
+    }
+
+    #[test]
+    fn inverse_patches_restore_the_original(root in arb_value(), patches in prop::collection::vec(arb_patch(), 0..8)) {
+        if let Ok((patched, inverse)) = apply_with_inverse(root.clone(), patches) {
+            prop_assert_eq!(apply(patched, inverse), Ok(root));
+        }
+    }
+
+    // documents are compared as strings, since that also checks key order with `preserve_order`
+    #[test]
+    fn apply_atomic_commits_or_restores_exactly(
+        root in arb_value(),
+        patches in prop::collection::vec(arb_patch(), 0..8),
+        strict in any::<bool>(),
+    ) {
+        let options = ApplyOptions::default().strict(strict);
+        let mut in_place = root.clone();
+        match apply_atomic_with(&mut in_place, &patches, &options) {
+            Ok(()) => {
+                let expected = apply_with(root, patches, &options).unwrap();
+                prop_assert_eq!(in_place.to_string(), expected.to_string());
+            }
+            Err(error) => {
+                prop_assert_eq!(Err(error), apply_with(root.clone(), patches, &options));
+                prop_assert_eq!(in_place.to_string(), root.to_string());
+            }
+        }
+    }
+}
+
+#[test]
+fn root_edge_cases_dont_panic() {
+    let roots = vec![
+        json!(null),
+        json!(1),
+        json!([]),
+        json!({}),
+        json!([[]]),
+        json!({"": {}}),
+    ];
+    let paths = vec!["", "/", "/-", "/0", "//", "/-/-", "/18446744073709551616"];
+    for root in roots {
+        for path in &paths {
+            for from in &paths {
+                let patches = vec![
+                    Patch::Add {
+                        path: Path::new(path),
+                        value: json!(1),
+                    },
+                    Patch::Remove {
+                        path: Path::new(path),
+                    },
+                    Patch::Replace {
+                        path: Path::new(path),
+                        value: json!(1),
+                    },
+                    Patch::Copy {
+                        from: Path::new(from),
+                        path: Path::new(path),
+                    },
+                    Patch::Move {
+                        from: Path::new(from),
+                        path: Path::new(path),
+                    },
+                    Patch::Test {
+                        path: Path::new(path),
+                        value: json!(1),
+                    },
+                ];
+                for patch in patches {
+                    let _ = apply_single(root.clone(), patch);
+                }
+            }
+        }
+    }
+}
@@ -1,17 +1,74 @@
 mod add;
 mod copy;
+#[cfg(test)]
+mod fuzz;
 mod r#move;
+mod pointer;
 mod remove;
 mod replace;
 mod test;
+mod undo;
 
 use serde_json::Value;
 
-use super::Patch;
+use super::{OpKind, Patch};
 use crate::errors::Error;
+use undo::Undo;
+
+pub use pointer::{get, get_mut, set, take};
+
+/// Options controlling how patches are applied
+///
+/// By default, patches are applied in strict mode, which enforces the error cases in [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902):
+///  - `remove` and `replace` fail if the target location doesn't exist
+///  - `move` fails if `from` is a proper prefix of `path`
+///  - array indices must not have a sign or leading zeros
+///
+/// Disabling strict mode restores the more forgiving behaviour of earlier versions:
+/// ```rust
+/// # use jatch::{apply_with, ApplyOptions, Patch, Path};
+/// # use serde_json::json;
+/// let patch = Patch::Remove {
+///   path: Path::new("/missing"),
+/// };
+/// assert!(apply_with(json!({}), vec![patch.clone()], &ApplyOptions::default()).is_err());
+///
+/// let lenient = ApplyOptions::default().strict(false);
+/// assert_eq!(apply_with(json!({}), vec![patch], &lenient).unwrap(), json!({}));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyOptions {
+    pub(crate) strict: bool,
+    pub(crate) skip_after_failed_test: bool,
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            skip_after_failed_test: false,
+        }
+    }
+}
+
+impl ApplyOptions {
+    /// Enable or disable strict RFC 6902 conformance
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// When a `test` operation fails, skip the following operations until the next `test`
+    ///
+    /// This only affects [apply_best_effort], since every other entry point stops at the first failure
+    pub fn skip_after_failed_test(mut self, skip: bool) -> Self {
+        self.skip_after_failed_test = skip;
+        self
+    }
+}
 
 /// Applies a single JSON Patch to a JSON document
-/// 
+///
 /// For example:
 /// ```rust
 /// # use jatch::{Patch, Path, apply_single};
@@ -19,7 +76,7 @@ use crate::errors::Error;
 /// let root = json!({"foo": "bar"});
 /// let patch = Patch::Add {
 ///   value: json!("world"),
-///   path: Path::new("/hello"), 
+///   path: Path::new("/hello"),
 /// };
 /// let root = apply_single(root, patch).unwrap();
 /// assert_eq!(root, json!({
@@ -27,20 +84,81 @@ use crate::errors::Error;
 ///    "hello": "world",
 /// }));
 /// ```
-pub fn apply_single(root: Value, patch: Patch) -> Result<Value, Error> {
+pub fn apply_single(mut root: Value, patch: Patch) -> Result<Value, Error> {
+    apply_owned(&mut root, patch, &ApplyOptions::default())?;
+    Ok(root)
+}
+
+/// Applies a single JSON Patch to a JSON document in place
+///
+/// ```rust
+/// # use jatch::{Patch, Path, apply_single_mut};
+/// # use serde_json::json;
+/// let mut root = json!({"foo": "bar"});
+/// let patch = Patch::Replace {
+///   value: json!("baz"),
+///   path: Path::new("/foo"),
+/// };
+/// apply_single_mut(&mut root, &patch).unwrap();
+/// assert_eq!(root, json!({"foo": "baz"}));
+/// ```
+pub fn apply_single_mut(root: &mut Value, patch: &Patch) -> Result<(), Error> {
+    apply_borrowed(root, patch, &ApplyOptions::default()).map(drop)
+}
+
+// the two functions below are identical, except that `apply_owned` can move values out of the patch rather than cloning them
+// each operation either succeeds, returning how to undo it (if it changed anything), or fails and leaves `root` unchanged
+fn apply_owned(
+    root: &mut Value,
+    patch: Patch,
+    options: &ApplyOptions,
+) -> Result<Option<Undo>, Error> {
     match patch {
-        Patch::Add { value, path } => add::add(root, value, path),
-        Patch::Remove { path } => remove::remove(root, path),
-        Patch::Replace { value, path } => replace::replace(root, value, path),
-        Patch::Copy { from, path } => copy::copy(root, from, path),
-        Patch::Move { from, path } => r#move::r#move(root, from, path), // 'move' is a keyword
-        Patch::Test { value, path } => test::test(root, value, path),
+        Patch::Add { value, path } => add::add(root, value, &path, options).map(Some),
+        Patch::Remove { path } => Ok(remove::remove(root, &path, options)?.map(Undo::Remove)),
+        Patch::Replace { value, path } => replace::replace(root, value, &path, options).map(Some),
+        Patch::Copy { from, path } => copy::copy(root, &from, &path, options).map(Some),
+        Patch::Move { from, path } => r#move::r#move(root, &from, &path, options), // 'move' is a keyword
+        Patch::Test { value, path } => test::test(root, &value, &path, options).map(|_| None),
+    }
+}
+
+fn apply_borrowed(
+    root: &mut Value,
+    patch: &Patch,
+    options: &ApplyOptions,
+) -> Result<Option<Undo>, Error> {
+    match patch {
+        Patch::Add { value, path } => add::add(root, value.clone(), path, options).map(Some),
+        Patch::Remove { path } => Ok(remove::remove(root, path, options)?.map(Undo::Remove)),
+        Patch::Replace { value, path } => {
+            replace::replace(root, value.clone(), path, options).map(Some)
+        }
+        Patch::Copy { from, path } => copy::copy(root, from, path, options).map(Some),
+        Patch::Move { from, path } => r#move::r#move(root, from, path, options),
+        Patch::Test { value, path } => test::test(root, value, path, options).map(|_| None),
+    }
+}
+
+fn operation_error(index: usize, patch: &Patch) -> impl FnOnce(Error) -> Error {
+    let op = patch.kind();
+    move |error| Error::Operation {
+        index,
+        op,
+        error: Box::new(error),
     }
 }
 
 /// Applies a collection of JSON Patches to a JSON document
 /// The patches are applied in order, and if any individual patch fails, the whole function fails
-/// 
+/// The returned error is an [Error::Operation], which records which patch failed
+///
+/// This function never panics: any patch that can't be applied to the document results in an [Error]
+///
+/// Patches are applied with the default [ApplyOptions], which are strict. Before strict mode was added, patches that
+/// removed or replaced missing values, moved a value into its own child, or used array indices such as `01` or `+1`
+/// were applied anyway, and now fail. Use [apply_with] with [ApplyOptions::strict] set to `false` to keep that behaviour
+///
 /// For example:
 /// ```rust
 /// # use jatch::{Patch, Path, apply};
@@ -52,7 +170,7 @@ pub fn apply_single(root: Value, patch: Patch) -> Result<Value, Error> {
 ///     path: Path::new("/hello"),
 ///   },
 ///   Patch::Remove {
-///     path: Path::new("/foo"), 
+///     path: Path::new("/foo"),
 ///   },
 /// ];
 /// let root = apply(root, patches).unwrap();
@@ -60,16 +178,242 @@ pub fn apply_single(root: Value, patch: Patch) -> Result<Value, Error> {
 ///    "hello": "world",
 /// }));
 /// ```
-pub fn apply(
+pub fn apply(root: Value, patches: impl IntoIterator<Item = Patch>) -> Result<Value, Error> {
+    apply_with(root, patches, &ApplyOptions::default())
+}
+
+/// Applies a collection of JSON Patches to a JSON document, using the given [ApplyOptions]
+pub fn apply_with(
     mut root: Value,
     patches: impl IntoIterator<Item = Patch>,
+    options: &ApplyOptions,
 ) -> Result<Value, Error> {
-    for patch in patches {
-        root = apply_single(root, patch)?;
+    for (index, patch) in patches.into_iter().enumerate() {
+        let to_error = operation_error(index, &patch);
+        apply_owned(&mut root, patch, options).map_err(to_error)?;
     }
     Ok(root)
 }
 
+/// Applies a collection of JSON Patches to a JSON document in place
+///
+/// Unlike [apply], this modifies each value where it is, without detaching and reinserting its parents, so it is cheaper for deep and wide documents.
+/// The patches are applied in order, and if any individual patch fails, the function stops and returns an [Error::Operation].
+/// Patches that were applied before the failing patch are not undone
+/// ```rust
+/// # use jatch::{Patch, Path, apply_mut};
+/// # use serde_json::json;
+/// let mut root = json!({"foo": ["bar"]});
+/// let patches = [
+///   Patch::Add {
+///     value: json!("baz"),
+///     path: Path::new("/foo/0"),
+///   },
+///   Patch::Move {
+///     from: Path::new("/foo"),
+///     path: Path::new("/qux"),
+///   },
+/// ];
+/// apply_mut(&mut root, &patches).unwrap();
+/// assert_eq!(root, json!({"qux": ["baz", "bar"]}));
+/// ```
+pub fn apply_mut(root: &mut Value, patches: &[Patch]) -> Result<(), Error> {
+    for (index, patch) in patches.iter().enumerate() {
+        apply_borrowed(root, patch, &ApplyOptions::default())
+            .map_err(operation_error(index, patch))?;
+    }
+    Ok(())
+}
+
+/// What happened to a single operation passed to [apply_best_effort]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationReport {
+    /// The index of the operation
+    pub index: usize,
+    /// The kind of the operation
+    pub op: OpKind,
+    /// Whether the operation was applied
+    pub status: OperationStatus,
+}
+
+/// The outcome of a single operation passed to [apply_best_effort]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum OperationStatus {
+    /// The operation was applied
+    Applied,
+    /// The operation failed, and the document was left as it was before the operation
+    Failed(Error),
+    /// The operation wasn't attempted, because a preceding `test` failed
+    ///
+    /// See [ApplyOptions::skip_after_failed_test]
+    Skipped,
+}
+
+/// Applies every operation that can be applied to a JSON document, skipping those that fail
+///
+/// Returns the final document along with a report of what happened to each operation, in order.
+/// A failing operation leaves the document as it was before that operation, so later operations see a consistent document
+/// ```rust
+/// # use jatch::{apply_best_effort, ApplyOptions, OperationStatus, Patch, Path};
+/// # use serde_json::json;
+/// let patches = vec![
+///   Patch::Remove {
+///     path: Path::new("/missing"),
+///   },
+///   Patch::Add {
+///     path: Path::new("/foo"),
+///     value: json!("bar"),
+///   },
+/// ];
+/// let (root, report) = apply_best_effort(json!({}), patches, &ApplyOptions::default());
+/// assert_eq!(root, json!({"foo": "bar"}));
+/// assert!(matches!(report[0].status, OperationStatus::Failed(_)));
+/// assert_eq!(report[1].status, OperationStatus::Applied);
+/// ```
+pub fn apply_best_effort(
+    mut root: Value,
+    patches: impl IntoIterator<Item = Patch>,
+    options: &ApplyOptions,
+) -> (Value, Vec<OperationReport>) {
+    let mut report = vec![];
+    let mut skipping = false;
+    for (index, patch) in patches.into_iter().enumerate() {
+        let op = patch.kind();
+        let status = if skipping && op != OpKind::Test {
+            OperationStatus::Skipped
+        } else {
+            match apply_owned(&mut root, patch, options) {
+                Ok(_) => OperationStatus::Applied,
+                Err(error) => OperationStatus::Failed(error),
+            }
+        };
+        if op == OpKind::Test {
+            skipping = options.skip_after_failed_test && status != OperationStatus::Applied;
+        }
+        report.push(OperationReport { index, op, status });
+    }
+    (root, report)
+}
+
+/// Applies a collection of JSON Patches to a JSON document, also returning the patches that undo the change
+///
+/// Applying the returned patches to the returned document restores the original document.
+/// With the `preserve_order` feature, object members that are restored by the inverse patches may be in a different position
+/// ```rust
+/// # use jatch::{apply, apply_with_inverse, Patch, Path};
+/// # use serde_json::json;
+/// let root = json!({"foo": "bar", "baz": [1, 2]});
+/// let patches = vec![
+///   Patch::Remove {
+///     path: Path::new("/foo"),
+///   },
+///   Patch::Move {
+///     from: Path::new("/baz/0"),
+///     path: Path::new("/baz/-"),
+///   },
+/// ];
+/// let (patched, inverse) = apply_with_inverse(root.clone(), patches).unwrap();
+/// assert_eq!(patched, json!({"baz": [2, 1]}));
+/// assert_eq!(inverse, vec![
+///   Patch::Move {
+///     from: Path::new("/baz/1"),
+///     path: Path::new("/baz/0"),
+///   },
+///   Patch::Add {
+///     path: Path::new("/foo"),
+///     value: json!("bar"),
+///   },
+/// ]);
+/// assert_eq!(apply(patched, inverse).unwrap(), root);
+/// ```
+pub fn apply_with_inverse(
+    mut root: Value,
+    patches: impl IntoIterator<Item = Patch>,
+) -> Result<(Value, Vec<Patch>), Error> {
+    let mut inverse = vec![];
+    for (index, patch) in patches.into_iter().enumerate() {
+        let to_error = operation_error(index, &patch);
+        if let Some(undo) =
+            apply_owned(&mut root, patch, &ApplyOptions::default()).map_err(to_error)?
+        {
+            undo.push_inverse(&mut inverse);
+        }
+    }
+    inverse.reverse();
+    Ok((root, inverse))
+}
+
+/// Applies a collection of JSON Patches to a JSON document in place, as a single transaction
+///
+/// Either every patch is applied, or `root` is left exactly as it was, including the order of object keys with the `preserve_order` feature.
+/// Rather than cloning the document up front, each operation records how to undo itself, and these are replayed in reverse if a later patch fails
+/// ```rust
+/// # use jatch::{Patch, Path, apply_atomic};
+/// # use serde_json::json;
+/// let mut root = json!({"foo": ["bar"]});
+/// let patches = [
+///   Patch::Add {
+///     value: json!("baz"),
+///     path: Path::new("/foo/0"),
+///   },
+///   Patch::Remove {
+///     path: Path::new("/missing"),
+///   },
+/// ];
+/// assert!(apply_atomic(&mut root, &patches).is_err());
+/// assert_eq!(root, json!({"foo": ["bar"]}));
+/// ```
+pub fn apply_atomic(root: &mut Value, patches: &[Patch]) -> Result<(), Error> {
+    apply_atomic_with(root, patches, &ApplyOptions::default())
+}
+
+/// Applies a collection of JSON Patches to a JSON document in place, as a single transaction, using the given [ApplyOptions]
+pub fn apply_atomic_with(
+    root: &mut Value,
+    patches: &[Patch],
+    options: &ApplyOptions,
+) -> Result<(), Error> {
+    let mut undos = Vec::with_capacity(patches.len());
+    for (index, patch) in patches.iter().enumerate() {
+        match apply_borrowed(root, patch, options) {
+            Ok(undo) => undos.extend(undo),
+            Err(error) => {
+                for undo in undos.into_iter().rev() {
+                    let reverted = undo::revert(root, undo);
+                    debug_assert!(reverted.is_ok(), "failed to revert: {:?}", reverted);
+                }
+                return Err(operation_error(index, patch)(error));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies the same JSON Patches to many documents in parallel, each as a single transaction
+///
+/// Returns the result for each document, in order. As with [apply_atomic], each document is either fully patched or left exactly as it was
+/// ```rust
+/// # use jatch::{par_apply_atomic, Patch, Path};
+/// # use serde_json::json;
+/// let mut documents = vec![json!({"a": 1}), json!({}), json!({"a": 2})];
+/// let patches = [Patch::Replace {
+///   path: Path::new("/a"),
+///   value: json!(3),
+/// }];
+/// let results = par_apply_atomic(&mut documents, &patches);
+/// assert!(results[1].is_err());
+/// assert_eq!(documents, vec![json!({"a": 3}), json!({}), json!({"a": 3})]);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_apply_atomic(documents: &mut [Value], patches: &[Patch]) -> Vec<Result<(), Error>> {
+    use rayon::prelude::*;
+    documents
+        .par_iter_mut()
+        .map(|document| apply_atomic(document, patches))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::read_to_string;
@@ -82,10 +426,12 @@ mod tests {
     #[derive(Deserialize, Debug, Clone)]
     struct TestCase {
         doc: Value,
-        patch: Vec<Patch>,
+        patch: Value,
         expected: Option<Value>,
         error: Option<String>,
         comment: Option<String>,
+        #[serde(default)]
+        disabled: bool,
     }
 
     fn load_tests() -> Vec<Value> {
@@ -96,36 +442,34 @@ mod tests {
         v
     }
 
+    // a patch that fails to deserialize counts as an error, since it could never be applied
+    fn run_test_case(test_case: TestCase) -> Result<Value, String> {
+        let patch = from_value::<Vec<Patch>>(test_case.patch).map_err(|e| e.to_string())?;
+        apply(test_case.doc, patch).map_err(|e| e.to_string())
+    }
+
     fn test_single(test_json: Value, index: usize) {
-        if test_json.get("expected").is_some() {
-            assert!(did_test_succeed(test_json, index));
-        } else {
-            assert!(!did_test_succeed(test_json, index));
+        let test_case = from_value::<TestCase>(test_json.clone()).unwrap();
+        if test_case.disabled {
+            return;
         }
-    }
 
-    fn did_test_succeed(test_json: Value, index: usize) -> bool {
-        if let Some(expected) = test_json.get("expected") {
-            let expected = expected.clone();
-            if let Some(root) = test_json.get("doc") {
-                let root = root.clone();
-                if let Some(patch) = test_json.get("patch") {
-                    if let Ok(patch) = from_value::<Vec<Patch>>(patch.clone()) {
-                        let actual = apply(root, patch);
-                        println!("running test for {}, number {}", test_json, index);
-                        let actual = actual.map(|value| assert_eq!(value, expected));
-                        actual.is_ok()
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
+        println!("running test {}: {}", index, test_json);
+        let comment = test_case.comment.clone().unwrap_or_default();
+        match (test_case.expected.clone(), test_case.error.clone()) {
+            (Some(expected), _) => {
+                assert_eq!(run_test_case(test_case), Ok(expected), "{}", comment)
+            }
+            (None, Some(error)) => assert!(
+                run_test_case(test_case).is_err(),
+                "expected error '{}': {}",
+                error,
+                comment
+            ),
+            (None, None) => {
+                // no expectations, but it should at least not panic
+                let _ = run_test_case(test_case);
             }
-        } else {
-            false
         }
     }
 
@@ -136,4 +480,205 @@ mod tests {
             .enumerate()
             .for_each(|(index, value)| test_single(value.to_owned(), index));
     }
+
+    #[test]
+    fn best_effort_should_skip_until_the_next_test() {
+        use crate::Path;
+        use serde_json::json;
+
+        let patches = vec![
+            Patch::Test {
+                path: Path::new("/version"),
+                value: json!(2),
+            },
+            Patch::Add {
+                path: Path::new("/a"),
+                value: json!(1),
+            },
+            Patch::Test {
+                path: Path::new("/version"),
+                value: json!(1),
+            },
+            Patch::Remove {
+                path: Path::new("/missing"),
+            },
+            Patch::Add {
+                path: Path::new("/b"),
+                value: json!(2),
+            },
+        ];
+        let statuses = |report: Vec<OperationReport>| {
+            report
+                .into_iter()
+                .map(|report| match report.status {
+                    OperationStatus::Applied => "applied",
+                    OperationStatus::Failed(_) => "failed",
+                    OperationStatus::Skipped => "skipped",
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (root, report) = apply_best_effort(
+            json!({"version": 1}),
+            patches.clone(),
+            &ApplyOptions::default(),
+        );
+        assert_eq!(root, json!({"version": 1, "a": 1, "b": 2}));
+        assert_eq!(
+            statuses(report),
+            vec!["failed", "applied", "applied", "failed", "applied"]
+        );
+
+        let options = ApplyOptions::default().skip_after_failed_test(true);
+        let (root, report) = apply_best_effort(json!({"version": 1}), patches, &options);
+        assert_eq!(root, json!({"version": 1, "b": 2}));
+        assert_eq!(report[3].index, 3);
+        assert_eq!(report[3].op, OpKind::Remove);
+        assert_eq!(
+            statuses(report),
+            vec!["failed", "skipped", "applied", "failed", "applied"]
+        );
+    }
+
+    #[test]
+    fn inverse_patches_should_restore_the_original_document() {
+        for (index, test_json) in load_tests().into_iter().enumerate() {
+            let test_case = from_value::<TestCase>(test_json).unwrap();
+            let patches = match from_value::<Vec<Patch>>(test_case.patch) {
+                Ok(patches) => patches,
+                Err(_) => continue,
+            };
+            let (patched, inverse) = match apply_with_inverse(test_case.doc.clone(), patches) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            assert_eq!(
+                apply(patched, inverse.clone()),
+                Ok(test_case.doc),
+                "test {}, inverse {:?}",
+                index,
+                inverse
+            );
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_apply_atomic_should_agree_with_apply_atomic() {
+        use crate::Path;
+        use serde_json::json;
+
+        let patches = [
+            Patch::Add {
+                path: Path::new("/list/-"),
+                value: json!("new"),
+            },
+            Patch::Remove {
+                path: Path::new("/list/0"),
+            },
+        ];
+        let documents = (0..100)
+            .map(|i| match i % 3 {
+                0 => json!({}),
+                _ => json!({ "list": (0..i).collect::<Vec<_>>() }),
+            })
+            .collect::<Vec<_>>();
+
+        let mut sequential = documents.clone();
+        let expected = sequential
+            .iter_mut()
+            .map(|document| apply_atomic(document, &patches))
+            .collect::<Vec<_>>();
+        let mut parallel = documents;
+        assert_eq!(par_apply_atomic(&mut parallel, &patches), expected);
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn apply_atomic_should_roll_back_every_kind_of_operation() {
+        use crate::Path;
+        use serde_json::json;
+
+        let root = json!({"a": [1, 2, 3], "b": {"c": 1, "d": 2}, "e": null});
+        let patches = vec![
+            Patch::Add {
+                path: Path::new("/a/1"),
+                value: json!(4),
+            },
+            Patch::Add {
+                path: Path::new("/b/c"),
+                value: json!(5),
+            },
+            Patch::Remove {
+                path: Path::new("/a/0"),
+            },
+            Patch::Replace {
+                path: Path::new("/e"),
+                value: json!(6),
+            },
+            Patch::Copy {
+                from: Path::new("/b"),
+                path: Path::new("/a/-"),
+            },
+            Patch::Move {
+                from: Path::new("/b/d"),
+                path: Path::new("/e"),
+            },
+            Patch::Move {
+                from: Path::new("/a"),
+                path: Path::new("/f"),
+            },
+            Patch::Test {
+                path: Path::new("/f/3/c"),
+                value: json!(5),
+            },
+            Patch::Remove {
+                path: Path::new("/missing"),
+            },
+        ];
+
+        let mut in_place = root.clone();
+        let error = apply_atomic(&mut in_place, &patches).unwrap_err();
+        assert!(matches!(error, Error::Operation { index: 8, .. }));
+        assert_eq!(in_place.to_string(), root.to_string());
+
+        apply_atomic(&mut in_place, &patches[..8]).unwrap();
+        assert_eq!(
+            in_place,
+            json!({"b": {"c": 5}, "e": 2, "f": [4, 2, 3, {"c": 5, "d": 2}]})
+        );
+    }
+
+    #[test]
+    fn errors_should_record_the_failing_operation() {
+        use crate::{errors::PathErrorKind, Path};
+        use serde_json::json;
+
+        let patches = vec![
+            Patch::Add {
+                path: Path::new("/a"),
+                value: json!({}),
+            },
+            Patch::Test {
+                path: Path::new("/a"),
+                value: json!({}),
+            },
+            Patch::Replace {
+                path: Path::new("/a/b/c"),
+                value: json!(1),
+            },
+        ];
+        assert_eq!(
+            apply(json!({}), patches),
+            Err(Error::Operation {
+                index: 2,
+                op: OpKind::Replace,
+                error: Box::new(Error::PathDoesntExist {
+                    path: Path::new("/a/b/c"),
+                    prefix: Path::new("/a/b"),
+                    kind: PathErrorKind::KeyNotFound,
+                }),
+            })
+        );
+    }
 }
@@ -1,15 +1,153 @@
 use serde_json::Value;
 
-use crate::{Path, errors::Error, patch::walk::walk};
+use crate::{errors::Error, patch::walk::walk, Path};
 
-use super::{add::add, remove::remove};
+use super::{
+    add::{add, try_add},
+    remove::remove,
+    undo::{insert_at, revert, Removed, Undo},
+    ApplyOptions,
+};
+
+/// Move the value at `from` to `path`, returning how to undo the change
+///
+/// If the value can't be added at `path`, it is put back at `from`, so `root` is left unchanged.
+/// Returns `None` if `from` and `path` are equal, since nothing changes
+pub fn r#move(
+    root: &mut Value,
+    from: &Path,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Option<Undo>, Error> {
+    // a location can't be moved into one of its own children
+    if options.strict && path.starts_with(from) && path != from {
+        return Err(Error::MoveIntoChild {
+            from: from.clone(),
+            path: path.clone(),
+        });
+    }
 
-pub fn r#move(root: Value, from: Path, path: Path) -> Result<Value, Error> {
     if from == path {
-        Ok(root)
-    } else {
-        let value_to_move = walk(&root, from.clone())?.clone();
-        let removed = remove(root, from)?;
-        add(removed, value_to_move, path)
+        return walk(root, from, options).map(|_| None);
+    }
+
+    let removed = match remove(root, from, options)? {
+        Some(removed) => removed,
+        // `from` is either the root, or missing in lenient mode, so nothing was removed
+        None => {
+            let value = walk(root, from, options)?.clone();
+            return add(root, value, path, options).map(Some);
+        }
+    };
+
+    let Removed {
+        path: removed_from,
+        position,
+        value,
+    } = removed;
+    match try_add(root, value, path, options) {
+        Ok(undo) => {
+            let (path, overwritten) = match undo {
+                // moving a value into one of its parents overwrites the parent, which contained the value
+                // reversing that would be a move into a child, so the parent is recorded as it was instead
+                Undo::Overwrite { path, mut old } if removed_from.starts_with(&path) => {
+                    let moved = walk(root, &path, options)?.clone();
+                    let relative = removed_from.strip_prefix(&path).unwrap_or_else(Path::root);
+                    insert_at(&mut old, &relative, position, moved)?;
+                    return Ok(Some(Undo::Overwrite { path, old }));
+                }
+                Undo::Overwrite { path, old } => (path, Some(old)),
+                Undo::Insert { path } => (path, None),
+                _ => unreachable!("add only inserts or overwrites"),
+            };
+            Ok(Some(Undo::Move {
+                from: removed_from,
+                position,
+                path,
+                overwritten,
+            }))
+        }
+        // put the value back where it came from, so a failed move leaves `root` unchanged
+        Err(failed) => {
+            let (e, value) = *failed;
+            let removed = Removed {
+                path: removed_from,
+                position,
+                value,
+            };
+            revert(root, Undo::Remove(removed))?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn r#move(root: Value, from: Path, path: Path, options: &ApplyOptions) -> Result<Value, Error> {
+        let mut root = root;
+        super::r#move(&mut root, &from, &path, options).map(|_| root)
+    }
+
+    #[test]
+    fn move_into_own_child_should_fail() {
+        let root = json!({"a": {"b": {}}});
+        assert_eq!(
+            r#move(
+                root.clone(),
+                Path::new("/a"),
+                Path::new("/a/b/c"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::MoveIntoChild {
+                from: Path::new("/a"),
+                path: Path::new("/a/b/c"),
+            })
+        );
+        assert_eq!(
+            r#move(
+                root.clone(),
+                Path::new("/a"),
+                Path::new("/a"),
+                &ApplyOptions::default()
+            ),
+            Ok(root)
+        );
+    }
+
+    #[test]
+    fn move_into_parent_should_record_the_whole_parent() {
+        let mut root = json!({"a": {"b": {"c": 1}, "d": 2}});
+        let undo = super::r#move(
+            &mut root,
+            &Path::new("/a/b"),
+            &Path::new("/a"),
+            &ApplyOptions::default(),
+        );
+        assert_eq!(root, json!({"a": {"c": 1}}));
+        assert_eq!(
+            undo,
+            Ok(Some(Undo::Overwrite {
+                path: Path::new("/a"),
+                old: json!({"b": {"c": 1}, "d": 2}),
+            }))
+        );
+    }
+
+    #[test]
+    fn move_to_sibling_with_shared_prefix() {
+        let root = json!({"a": 1});
+        assert_eq!(
+            r#move(
+                root,
+                Path::new("/a"),
+                Path::new("/ab"),
+                &ApplyOptions::default()
+            ),
+            Ok(json!({"ab": 1}))
+        );
     }
 }
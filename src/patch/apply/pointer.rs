@@ -0,0 +1,208 @@
+// resolving a path against a document directly, with the same rules and errors as the patch operations
+
+use std::mem;
+
+use serde_json::Value;
+
+use crate::{
+    errors::Error,
+    patch::walk::{walk, walk_mut},
+    Pointer,
+};
+
+use super::{
+    add::{add_value, Added},
+    remove::remove_value,
+    ApplyOptions,
+};
+
+const OPTIONS: ApplyOptions = ApplyOptions {
+    strict: true,
+    skip_after_failed_test: false,
+};
+
+/// Get a reference to the value at `path`, which is resolved like the `path` of a [Patch::Test](crate::Patch::Test)
+///
+/// `path` can be a [Path](crate::Path) or a [PathRef](crate::PathRef), which is resolved without allocating
+/// ```rust
+/// # use jatch::{get, Path, PathRef};
+/// # use serde_json::json;
+/// let root = json!({"a": [1, {"b": 2}]});
+/// assert_eq!(get(&root, &Path::new("/a/1/b")).unwrap(), &json!(2));
+/// assert_eq!(get(&root, &PathRef::new("/a/1/b")).unwrap(), &json!(2));
+/// assert!(get(&root, &Path::new("/a/-")).is_err());
+/// ```
+pub fn get<'a>(root: &'a Value, path: &impl Pointer) -> Result<&'a Value, Error> {
+    walk(root, path, &OPTIONS)
+}
+
+/// Get a mutable reference to the value at `path`, which is resolved like [get]
+///
+/// ```rust
+/// # use jatch::{get_mut, Path};
+/// # use serde_json::json;
+/// let mut root = json!({"a": [1, 2]});
+/// *get_mut(&mut root, &Path::new("/a/0")).unwrap() = json!(3);
+/// assert_eq!(root, json!({"a": [3, 2]}));
+/// ```
+pub fn get_mut<'a>(root: &'a mut Value, path: &impl Pointer) -> Result<&'a mut Value, Error> {
+    walk_mut(root, path, &OPTIONS)
+}
+
+/// Remove the value at `path` and return it, like a [Patch::Remove](crate::Patch::Remove)
+///
+/// Removing an array element shifts the following elements down. Taking the root leaves `null` in its place, like
+/// [std::mem::take], whereas a [Patch::Remove](crate::Patch::Remove) of the root leaves the document unchanged
+/// ```rust
+/// # use jatch::{take, Path, PathRef};
+/// # use serde_json::json;
+/// let mut root = json!({"a": [1, 2, 3]});
+/// assert_eq!(take(&mut root, &Path::new("/a/0")).unwrap(), json!(1));
+/// assert_eq!(take(&mut root, &PathRef::new("/a/1")).unwrap(), json!(3));
+/// assert_eq!(root, json!({"a": [2]}));
+/// assert_eq!(take(&mut root, &Path::root()).unwrap(), json!({"a": [2]}));
+/// assert_eq!(root, json!(null));
+/// ```
+pub fn take(root: &mut Value, path: &impl Pointer) -> Result<Value, Error> {
+    match remove_value(root, path, &OPTIONS)? {
+        Some(taken) => Ok(taken.value),
+        // strict mode only returns `None` for the root
+        None => Ok(mem::take(root)),
+    }
+}
+
+/// Set the value at `path` to `value`, like a [Patch::Add](crate::Patch::Add), returning the value it overwrote
+///
+/// An object member is inserted or overwritten, while setting an array element inserts it, shifting the following
+/// elements up, so nothing is overwritten. `-` appends to an array
+/// ```rust
+/// # use jatch::{set, Path, PathRef};
+/// # use serde_json::json;
+/// let mut root = json!({"a": 1, "b": [1, 2]});
+/// assert_eq!(set(&mut root, &Path::new("/a"), json!(2)).unwrap(), Some(json!(1)));
+/// assert_eq!(set(&mut root, &PathRef::new("/b/-"), json!(3)).unwrap(), None);
+/// assert_eq!(root, json!({"a": 2, "b": [1, 2, 3]}));
+/// ```
+pub fn set(root: &mut Value, path: &impl Pointer, value: Value) -> Result<Option<Value>, Error> {
+    match add_value(root, value, path, &OPTIONS) {
+        Ok(Added::Overwrote(old)) => Ok(Some(old)),
+        Ok(Added::Inserted { .. }) => Ok(None),
+        Err(failed) => Err(failed.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{apply_single, Patch, Path, PathErrorKind, PathRef};
+
+    #[test]
+    fn pointer_functions_should_agree_with_patches() {
+        let root = json!({"a": [1, {"b": 2}], "c": null});
+        let paths = vec![
+            "", "/a", "/a/1/b", "/a/-", "/a/01", "/a/5", "/c/d", "/missing",
+        ];
+        for s in paths {
+            let path = Path::new(s);
+
+            let test = Patch::Test {
+                path: path.clone(),
+                value: get(&root, &path).cloned().unwrap_or(json!("missing")),
+            };
+            let expected = apply_single(root.clone(), test).map(drop);
+            assert_eq!(get(&root, &path).map(drop), expected, "get {}", s);
+            assert_eq!(
+                get_mut(&mut root.clone(), &path).map(drop),
+                expected,
+                "get_mut {}",
+                s
+            );
+            let path_ref = PathRef::new(s);
+            assert_eq!(get(&root, &path_ref), get(&root, &path), "get {}", s);
+            assert_eq!(
+                get_mut(&mut root.clone(), &path_ref).map(drop),
+                expected,
+                "get_mut {}",
+                s
+            );
+
+            let mut taken = root.clone();
+            let removed = Patch::Remove { path: path.clone() };
+            match (take(&mut taken, &path), apply_single(root.clone(), removed)) {
+                // unlike removing the root, taking it leaves null behind
+                (Ok(value), Ok(_)) if path.is_empty() => {
+                    assert_eq!((value, &taken), (root.clone(), &json!(null)), "take {}", s)
+                }
+                (Ok(_), Ok(expected)) => assert_eq!(taken, expected, "take {}", s),
+                (Err(error), Err(expected)) => assert_eq!(error, expected, "take {}", s),
+                (actual, expected) => panic!("take {}: {:?} != {:?}", s, actual, expected),
+            }
+            let mut taken_by_ref = root.clone();
+            assert_eq!(
+                take(&mut taken_by_ref, &path_ref),
+                take(&mut root.clone(), &path),
+                "take {}",
+                s
+            );
+            assert_eq!(taken_by_ref, taken, "take {}", s);
+
+            let mut set_root = root.clone();
+            let added = Patch::Add {
+                path: path.clone(),
+                value: json!(true),
+            };
+            match (
+                set(&mut set_root, &path, json!(true)),
+                apply_single(root.clone(), added),
+            ) {
+                (Ok(_), Ok(expected)) => assert_eq!(set_root, expected, "set {}", s),
+                (Err(error), Err(expected)) => assert_eq!(error, expected, "set {}", s),
+                (actual, expected) => panic!("set {}: {:?} != {:?}", s, actual, expected),
+            }
+            let mut set_by_ref = root.clone();
+            assert_eq!(
+                set(&mut set_by_ref, &path_ref, json!(true)),
+                set(&mut root.clone(), &path, json!(true)),
+                "set {}",
+                s
+            );
+            assert_eq!(set_by_ref, set_root, "set {}", s);
+        }
+    }
+
+    #[test]
+    fn pointer_functions_should_report_why_paths_dont_resolve() {
+        let mut root = json!({"a": [1, 2]});
+        let error = |path: &str, prefix: &str, kind| Error::PathDoesntExist {
+            path: Path::new(path),
+            prefix: Path::new(prefix),
+            kind,
+        };
+
+        assert_eq!(
+            get(&root, &Path::new("/a/-")),
+            Err(error("/a/-", "/a/-", PathErrorKind::EndOfArray))
+        );
+        assert_eq!(
+            take(&mut root, &Path::new("/b/c")),
+            Err(error("/b/c", "/b", PathErrorKind::KeyNotFound))
+        );
+        assert_eq!(
+            set(&mut root, &Path::new("/a/3"), json!(3)),
+            Err(error(
+                "/a/3",
+                "/a/3",
+                PathErrorKind::IndexOutOfBounds { index: 3, len: 2 }
+            ))
+        );
+        assert_eq!(take(&mut root, &Path::root()), Ok(json!({"a": [1, 2]})));
+        assert_eq!(root, json!(null));
+        assert_eq!(
+            set(&mut root, &Path::root(), json!(1)),
+            Ok(Some(json!(null)))
+        );
+        assert_eq!(root, json!(1));
+    }
+}
@@ -1,39 +1,89 @@
 use serde_json::Value;
 
-use crate::{errors::Error, patch::walk::parse_array_index, Path};
-
-pub fn remove(mut root: Value, path: Path) -> Result<Value, Error> {
-    if let Some((head, tail)) = path.split_head() {
-        // modify the value in place
-        match root {
-            Value::Object(ref mut map) => {
-                if tail.is_empty() {
-                    map.remove(&head);
-                } else {
-                    let mut inner_map = map.remove(&head).ok_or(Error::PathDoesntExist)?;
-                    inner_map = remove(inner_map, tail)?;
-                    map.insert(head, inner_map);
-                }
-            }
-            Value::Array(ref mut vec) => {
-                let head_index = parse_array_index(vec, head)?;
-                if head_index < vec.len() {
-                    if tail.is_empty() {
-                        vec.remove(head_index);
-                    } else {
-                        let mut inner_value = vec.remove(head_index);
-                        inner_value = remove(inner_value, tail)?;
-                        vec.insert(head_index, inner_value);
-                    }
-                } else {
-                    return Err(Error::PathDoesntExist);
-                }
+use crate::{
+    errors::{Error, PathErrorKind},
+    patch::walk::{array_index, last_token_error, walk_to_parent_mut},
+    Path, Pointer,
+};
+
+use super::{
+    undo::{remove_key, Removed},
+    ApplyOptions,
+};
+
+/// Remove the value at `path`, returning it along with where it was removed from
+///
+/// Returns `None` if `path` refers to the root, or to a missing object member in lenient mode
+pub fn remove(
+    root: &mut Value,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Option<Removed>, Error> {
+    Ok(remove_value(root, path, options)?.map(|taken| Removed {
+        // in lenient mode, an index such as `01` is stored the way strict mode would spell it
+        path: if taken.from_array {
+            path.with_last(taken.position.to_string())
+        } else {
+            path.clone()
+        },
+        position: taken.position,
+        value: taken.value,
+    }))
+}
+
+/// A value removed by [remove_value]
+pub struct Taken {
+    /// The position of the value within its parent
+    pub position: usize,
+    /// Whether the value was an array element, rather than an object member
+    pub from_array: bool,
+    /// The value that was removed
+    pub value: Value,
+}
+
+/// Like [remove], but for any [Pointer], without building the path the value was removed from
+pub fn remove_value(
+    root: &mut Value,
+    path: &impl Pointer,
+    options: &ApplyOptions,
+) -> Result<Option<Taken>, Error> {
+    let (parent, segment) = match walk_to_parent_mut(root, path, options)? {
+        Some(parent) => parent,
+        None => return Ok(None),
+    };
+
+    match parent {
+        Value::Object(map) => match remove_key(map, &segment.token) {
+            Some((position, value)) => Ok(Some(Taken {
+                position,
+                from_array: false,
+                value,
+            })),
+            // removing a missing key is an error in strict mode, and a no-op otherwise
+            None if options.strict => Err(last_token_error(path, PathErrorKind::KeyNotFound)),
+            None => Ok(None),
+        },
+        Value::Array(vec) => {
+            let index = array_index(vec, segment.classify(), false, options)
+                .map_err(|kind| last_token_error(path, kind))?;
+            if index < vec.len() {
+                Ok(Some(Taken {
+                    position: index,
+                    from_array: true,
+                    value: vec.remove(index),
+                }))
+            } else {
+                Err(last_token_error(
+                    path,
+                    PathErrorKind::IndexOutOfBounds {
+                        index,
+                        len: vec.len(),
+                    },
+                ))
             }
-            _ => return Err(Error::PathDoesntExist),
         }
+        _ => Err(last_token_error(path, PathErrorKind::NotAContainer)),
     }
-
-    Ok(root)
 }
 
 #[cfg(test)]
@@ -42,30 +92,71 @@ mod test {
 
     use super::*;
 
+    fn remove_from(mut root: Value, path: &Path, options: &ApplyOptions) -> Result<Value, Error> {
+        remove(&mut root, path, options).map(|_| root)
+    }
+
     #[test]
     fn remove_from_array() {
         let root = json!([1, 2, 3]);
-        let without_first = remove(root, Path::new("/0")).unwrap();
+        let without_first = remove_from(root, &Path::new("/0"), &ApplyOptions::default()).unwrap();
         assert_eq!(without_first, json!([2, 3]));
 
-        let error = remove(without_first, Path::new("/-")).unwrap_err();
-        assert_eq!(error, Error::PathDoesntExist);
+        let error =
+            remove_from(without_first, &Path::new("/-"), &ApplyOptions::default()).unwrap_err();
+        assert_eq!(
+            error,
+            Error::PathDoesntExist {
+                path: Path::new("/-"),
+                prefix: Path::new("/-"),
+                kind: PathErrorKind::EndOfArray,
+            }
+        );
     }
 
     #[test]
     fn remove_from_object() {
         let root = json!({"a": 1, "b": 2});
-        let without_a = remove(root, Path::new("/a")).unwrap();
+        let without_a = remove_from(root, &Path::new("/a"), &ApplyOptions::default()).unwrap();
         assert_eq!(without_a, json!({"b": 2}));
 
-        let error = remove(without_a, Path::new("/b/c")).unwrap_err();
-        assert_eq!(error, Error::PathDoesntExist);
+        let error =
+            remove_from(without_a, &Path::new("/b/c"), &ApplyOptions::default()).unwrap_err();
+        assert_eq!(
+            error,
+            Error::PathDoesntExist {
+                path: Path::new("/b/c"),
+                prefix: Path::new("/b/c"),
+                kind: PathErrorKind::NotAContainer,
+            }
+        );
+    }
+
+    #[test]
+    fn remove_missing_key() {
+        let root = json!({"a": 1});
+        let error =
+            remove_from(root.clone(), &Path::new("/b"), &ApplyOptions::default()).unwrap_err();
+        assert_eq!(
+            error,
+            Error::PathDoesntExist {
+                path: Path::new("/b"),
+                prefix: Path::new("/b"),
+                kind: PathErrorKind::KeyNotFound,
+            }
+        );
+
+        let lenient = ApplyOptions::default().strict(false);
+        assert_eq!(
+            remove_from(root.clone(), &Path::new("/b"), &lenient),
+            Ok(root)
+        );
     }
 
     #[test]
     fn remove_from_deep_array() {
         let root = json!([1, 2, [3, 4, [4, 5, 6]]]);
-        let without_6 = remove(root, Path::new("/2/2/2")).unwrap();
+        let without_6 = remove_from(root, &Path::new("/2/2/2"), &ApplyOptions::default()).unwrap();
         assert_eq!(without_6, json!([1, 2, [3, 4, [4, 5]]]))
     }
 
@@ -81,7 +172,8 @@ mod test {
                 }
             }
         });
-        let without_e = remove(root, Path::new("/a/b/c/e")).unwrap();
+        let without_e =
+            remove_from(root, &Path::new("/a/b/c/e"), &ApplyOptions::default()).unwrap();
         assert_eq!(
             without_e,
             json!({
@@ -95,5 +187,33 @@ mod test {
             })
         );
     }
-}
 
+    #[test]
+    fn remove_should_return_removed_value() {
+        let mut root = json!({"a": 1, "b": [1, 2]});
+        let options = ApplyOptions::default();
+        let removed = remove(&mut root, &Path::new("/a"), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!((removed.path, removed.value), (Path::new("/a"), json!(1)));
+        let removed = remove(&mut root, &Path::new("/b/1"), &options);
+        assert_eq!(
+            removed,
+            Ok(Some(Removed {
+                path: Path::new("/b/1"),
+                position: 1,
+                value: json!(2)
+            }))
+        );
+        assert_eq!(root, json!({"b": [1]}));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn remove_should_preserve_key_order() {
+        let mut root = json!({"a": 1, "b": 2, "c": 3, "d": 4});
+        remove(&mut root, &Path::new("/b"), &ApplyOptions::default()).unwrap();
+        let keys = root.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "c", "d"]);
+    }
+}
@@ -1,12 +1,63 @@
+use std::mem;
+
 use serde_json::Value;
 
-use crate::{Path, errors::Error};
+use crate::{
+    errors::{Error, PathErrorKind},
+    patch::walk::{array_index, path_error, walk_to_parent_mut},
+    Path,
+};
 
-use super::{add::add, remove::remove};
+use super::{undo::Undo, ApplyOptions};
 
+/// Replace the value at `path` in place, returning how to undo the change
+///
+/// In lenient mode, replacing a missing object member adds it
+pub fn replace(
+    root: &mut Value,
+    value: Value,
+    path: &Path,
+    options: &ApplyOptions,
+) -> Result<Undo, Error> {
+    let (parent, segment) = match walk_to_parent_mut(root, path, options)? {
+        Some(parent) => parent,
+        None => {
+            return Ok(Undo::Overwrite {
+                path: Path::root(),
+                old: mem::replace(root, value),
+            })
+        }
+    };
+    let depth = path.len() - 1;
 
-pub fn replace(root: Value, value: Value, path: Path) -> Result<Value, Error> {
-  // potential to make this faster by doing it in a single pass
-  let without_old_value = remove(root, path.clone())?;
-  add(without_old_value, value, path)
-}
\ No newline at end of file
+    match parent {
+        Value::Object(map) => match map.get_mut(segment.token.as_ref()) {
+            Some(old) => Ok(Undo::Overwrite {
+                path: path.clone(),
+                old: mem::replace(old, value),
+            }),
+            None if !options.strict => {
+                map.insert(segment.token.into_owned(), value);
+                Ok(Undo::Insert { path: path.clone() })
+            }
+            None => Err(path_error(path, depth, PathErrorKind::KeyNotFound)),
+        },
+        Value::Array(vec) => {
+            let index = array_index(vec, segment.classify(), false, options)
+                .map_err(|kind| path_error(path, depth, kind))?;
+            let len = vec.len();
+            match vec.get_mut(index) {
+                Some(old) => Ok(Undo::Overwrite {
+                    path: path.with_last(index.to_string()),
+                    old: mem::replace(old, value),
+                }),
+                None => Err(path_error(
+                    path,
+                    depth,
+                    PathErrorKind::IndexOutOfBounds { index, len },
+                )),
+            }
+        }
+        _ => Err(path_error(path, depth, PathErrorKind::NotAContainer)),
+    }
+}
@@ -1,14 +1,23 @@
 use serde_json::Value;
 
-use crate::{errors::Error, patch::walk::walk, Path};
+use crate::{errors::Error, json_eq, patch::walk::walk, Path};
 
-pub fn test(root: Value, value: Value, path: Path) -> Result<Value, Error> {
-    if walk(&root, path)? == &value {
-        Ok(root)
+use super::ApplyOptions;
+
+pub fn test(root: &Value, value: &Value, path: &Path, options: &ApplyOptions) -> Result<(), Error> {
+    let actual = walk(root, path, options)?;
+    // numbers are compared by value, as required by RFC 6902 section 4.6
+    if json_eq(actual, value) {
+        Ok(())
     } else {
-        Err(Error::FailedTest)
+        Err(Error::FailedTest {
+            path: path.clone(),
+            expected: Box::new(value.clone()),
+            actual: Box::new(actual.clone()),
+        })
     }
 }
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -49,7 +58,26 @@ mod tests {
             "b": [1],
         });
 
-        assert!(matches!(apply_single(root, test), Err(Error::FailedTest)));
+        assert_eq!(
+            apply_single(root, test),
+            Err(Error::FailedTest {
+                path: Path::new("/a/c"),
+                expected: Box::new(json!(123)),
+                actual: Box::new(json!(234)),
+            })
+        );
+    }
+
+    #[test]
+    fn test_should_compare_numbers_by_value() {
+        let root: Value = serde_json::from_str(r#"{"a": [1.0, 2e1], "b": -0.0}"#).unwrap();
+        let check = |path: &str, value: Value| {
+            let path = Path::new(path);
+            test(&root, &value, &path, &ApplyOptions::default())
+        };
+        assert_eq!(check("/a", json!([1, 20])), Ok(()));
+        assert_eq!(check("/b", json!(0)), Ok(()));
+        assert!(check("/a/0", json!(1.5)).is_err());
     }
 
     #[test]
@@ -67,6 +95,9 @@ mod tests {
             "b": [1],
         });
 
-        assert!(matches!(apply_single(root, test), Err(Error::PathDoesntExist)));
+        assert!(matches!(
+            apply_single(root, test),
+            Err(Error::PathDoesntExist { .. })
+        ));
     }
 }
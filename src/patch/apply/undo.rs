@@ -0,0 +1,159 @@
+use std::mem;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    errors::Error,
+    patch::walk::{walk_mut, walk_to_parent_mut},
+    Patch, Path,
+};
+
+use super::{add::add, remove::remove, ApplyOptions};
+
+/// A record of a single change made to a document, with enough information to revert it exactly
+///
+/// Paths are concrete: array indices are always numeric, never `-`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Undo {
+    /// Nothing existed at `path`, and a value was inserted there
+    Insert { path: Path },
+    /// The value at `path` was `old`, and was overwritten in place
+    Overwrite { path: Path, old: Value },
+    /// A value was removed
+    Remove(Removed),
+    /// The value at `from` was moved to `path`, overwriting `overwritten` if it was an existing object member
+    Move {
+        from: Path,
+        position: usize,
+        path: Path,
+        overwritten: Option<Value>,
+    },
+}
+
+/// A value that was removed from a document
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Removed {
+    /// Where the value was removed from
+    pub path: Path,
+    /// The position of the value within its parent
+    ///
+    /// For objects, this is only meaningful with `preserve_order`, since otherwise keys are kept sorted
+    pub position: usize,
+    /// The value that was removed
+    pub value: Value,
+}
+
+/// Revert a change made to `root`, restoring it to exactly the state it was in before the change
+///
+/// This can only fail if `root` has been modified in some other way since the change was made
+pub(crate) fn revert(root: &mut Value, undo: Undo) -> Result<(), Error> {
+    match undo {
+        Undo::Insert { path } => take(root, &path).map(drop),
+        Undo::Overwrite { path, old } => put_back(root, &path, old).map(drop),
+        Undo::Remove(Removed {
+            path,
+            position,
+            value,
+        }) => insert_at(root, &path, position, value),
+        Undo::Move {
+            from,
+            position,
+            path,
+            overwritten,
+        } => {
+            let value = match overwritten {
+                Some(old) => put_back(root, &path, old)?,
+                None => take(root, &path)?,
+            };
+            insert_at(root, &from, position, value)
+        }
+    }
+}
+
+impl Undo {
+    /// Push the patches that revert this change onto `inverse`, in reverse order
+    ///
+    /// Unlike [revert], object members that are added back are not restored to their original position
+    pub(crate) fn push_inverse(self, inverse: &mut Vec<Patch>) {
+        match self {
+            Undo::Insert { path } => inverse.push(Patch::Remove { path }),
+            Undo::Overwrite { path, old } => inverse.push(Patch::Replace { path, value: old }),
+            Undo::Remove(Removed { path, value, .. }) => inverse.push(Patch::Add { path, value }),
+            Undo::Move {
+                from,
+                path,
+                overwritten,
+                ..
+            } => {
+                if let Some(old) = overwritten {
+                    inverse.push(Patch::Add {
+                        path: path.clone(),
+                        value: old,
+                    });
+                }
+                inverse.push(Patch::Move {
+                    from: path,
+                    path: from,
+                });
+            }
+        }
+    }
+}
+
+// the paths stored in an `Undo` may contain indices that are only accepted in lenient mode, and every path
+// resolved while reverting is known to exist, so lenient mode is used
+const OPTIONS: ApplyOptions = ApplyOptions {
+    strict: false,
+    skip_after_failed_test: false,
+};
+
+fn take(root: &mut Value, path: &Path) -> Result<Value, Error> {
+    match remove(root, path, &OPTIONS)? {
+        Some(removed) => Ok(removed.value),
+        None => Ok(mem::take(root)),
+    }
+}
+
+/// Overwrite the value at `path` with `old`, returning the value that was there
+fn put_back(root: &mut Value, path: &Path, old: Value) -> Result<Value, Error> {
+    walk_mut(root, path, &OPTIONS).map(|slot| mem::replace(slot, old))
+}
+
+/// Insert `value` at `path`, at `position` within its parent
+pub(crate) fn insert_at(
+    root: &mut Value,
+    path: &Path,
+    position: usize,
+    value: Value,
+) -> Result<(), Error> {
+    if let Some((Value::Object(map), segment)) = walk_to_parent_mut(root, path, &OPTIONS)? {
+        insert_key(map, position, segment.token.into_owned(), value);
+        return Ok(());
+    }
+    // for arrays, the index in `path` is the position, so adding is enough
+    add(root, value, path, &OPTIONS).map(drop)
+}
+
+/// Remove `key` from `map`, returning its position in the map along with its value
+///
+/// With `preserve_order`, a plain `remove` swaps the last key into the gap, so the remaining keys are shifted instead
+#[cfg(feature = "preserve_order")]
+pub(crate) fn remove_key(map: &mut Map<String, Value>, key: &str) -> Option<(usize, Value)> {
+    let position = map.keys().position(|k| k == key)?;
+    map.shift_remove(key).map(|value| (position, value))
+}
+
+#[cfg(not(feature = "preserve_order"))]
+pub(crate) fn remove_key(map: &mut Map<String, Value>, key: &str) -> Option<(usize, Value)> {
+    map.remove(key).map(|value| (0, value))
+}
+
+#[cfg(feature = "preserve_order")]
+fn insert_key(map: &mut Map<String, Value>, position: usize, key: String, value: Value) {
+    map.shift_insert(position.min(map.len()), key, value);
+}
+
+#[cfg(not(feature = "preserve_order"))]
+fn insert_key(map: &mut Map<String, Value>, _position: usize, key: String, value: Value) {
+    map.insert(key, value);
+}
@@ -1,6 +1,8 @@
 pub mod apply;
 pub mod walk;
 
+use std::fmt::Display;
+
 use crate::Path;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -63,6 +65,173 @@ pub enum Patch {
     },
 }
 
+impl Patch {
+    /// The kind of operation this patch performs
+    pub fn kind(&self) -> OpKind {
+        match self {
+            Patch::Add { .. } => OpKind::Add,
+            Patch::Remove { .. } => OpKind::Remove,
+            Patch::Replace { .. } => OpKind::Replace,
+            Patch::Copy { .. } => OpKind::Copy,
+            Patch::Move { .. } => OpKind::Move,
+            Patch::Test { .. } => OpKind::Test,
+        }
+    }
+
+    /// Borrow the value of this patch as a [PatchRef], which only copies the paths
+    pub fn to_patch_ref(&self) -> PatchRef<'_> {
+        match self {
+            Patch::Add { path, value } => PatchRef::Add {
+                path: path.clone(),
+                value,
+            },
+            Patch::Remove { path } => PatchRef::Remove { path: path.clone() },
+            Patch::Replace { path, value } => PatchRef::Replace {
+                path: path.clone(),
+                value,
+            },
+            Patch::Copy { from, path } => PatchRef::Copy {
+                from: from.clone(),
+                path: path.clone(),
+            },
+            Patch::Move { from, path } => PatchRef::Move {
+                from: from.clone(),
+                path: path.clone(),
+            },
+            Patch::Test { path, value } => PatchRef::Test {
+                path: path.clone(),
+                value,
+            },
+        }
+    }
+}
+
+/// A Json Patch operation that borrows its value, rather than owning it
+///
+/// This serializes exactly like the equivalent [Patch], so it can be streamed to a serializer without cloning the value
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+#[serde(tag = "op")]
+pub enum PatchRef<'a> {
+    /// See [Patch::Add]
+    #[serde(rename = "add")]
+    Add {
+        /// The path to add to
+        path: Path,
+        /// The value to add
+        value: &'a Value,
+    },
+    /// See [Patch::Remove]
+    #[serde(rename = "remove")]
+    Remove {
+        /// The path to remove from
+        path: Path,
+    },
+    /// See [Patch::Replace]
+    #[serde(rename = "replace")]
+    Replace {
+        /// The path to replace
+        path: Path,
+        /// The value to replace
+        value: &'a Value,
+    },
+    /// See [Patch::Copy]
+    #[serde(rename = "copy")]
+    Copy {
+        /// The path to copy from
+        from: Path,
+        /// The path to copy to
+        path: Path,
+    },
+    /// See [Patch::Move]
+    #[serde(rename = "move")]
+    Move {
+        /// The path to move from
+        from: Path,
+        /// the path to move to
+        path: Path,
+    },
+    /// See [Patch::Test]
+    #[serde(rename = "test")]
+    Test {
+        /// The path to test
+        path: Path,
+        /// The value expected at `path`
+        value: &'a Value,
+    },
+}
+
+impl PatchRef<'_> {
+    /// The kind of operation this patch performs
+    pub fn kind(&self) -> OpKind {
+        match self {
+            PatchRef::Add { .. } => OpKind::Add,
+            PatchRef::Remove { .. } => OpKind::Remove,
+            PatchRef::Replace { .. } => OpKind::Replace,
+            PatchRef::Copy { .. } => OpKind::Copy,
+            PatchRef::Move { .. } => OpKind::Move,
+            PatchRef::Test { .. } => OpKind::Test,
+        }
+    }
+
+    /// Clone the borrowed value into an owned [Patch]
+    pub fn to_patch(&self) -> Patch {
+        self.clone().into()
+    }
+}
+
+/// Clones the borrowed value, and moves the paths
+impl From<PatchRef<'_>> for Patch {
+    fn from(patch: PatchRef<'_>) -> Self {
+        match patch {
+            PatchRef::Add { path, value } => Patch::Add {
+                path,
+                value: value.clone(),
+            },
+            PatchRef::Remove { path } => Patch::Remove { path },
+            PatchRef::Replace { path, value } => Patch::Replace {
+                path,
+                value: value.clone(),
+            },
+            PatchRef::Copy { from, path } => Patch::Copy { from, path },
+            PatchRef::Move { from, path } => Patch::Move { from, path },
+            PatchRef::Test { path, value } => Patch::Test {
+                path,
+                value: value.clone(),
+            },
+        }
+    }
+}
+
+/// The kind of a [Patch] operation, without its arguments
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum OpKind {
+    /// [Patch::Add]
+    Add,
+    /// [Patch::Remove]
+    Remove,
+    /// [Patch::Replace]
+    Replace,
+    /// [Patch::Copy]
+    Copy,
+    /// [Patch::Move]
+    Move,
+    /// [Patch::Test]
+    Test,
+}
+
+impl Display for OpKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OpKind::Add => "add",
+            OpKind::Remove => "remove",
+            OpKind::Replace => "replace",
+            OpKind::Copy => "copy",
+            OpKind::Move => "move",
+            OpKind::Test => "test",
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use serde_json::json;
@@ -120,4 +289,42 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn patch_refs_should_serialize_like_patches() {
+        let value = json!({"a": [1, 2]});
+        let from = Path::new("/from");
+        let path = Path::new("/path");
+        let patches = vec![
+            PatchRef::Add {
+                path: path.clone(),
+                value: &value,
+            },
+            PatchRef::Remove { path: path.clone() },
+            PatchRef::Replace {
+                path: path.clone(),
+                value: &value,
+            },
+            PatchRef::Copy {
+                from: from.clone(),
+                path: path.clone(),
+            },
+            PatchRef::Move {
+                from,
+                path: path.clone(),
+            },
+            PatchRef::Test {
+                path,
+                value: &value,
+            },
+        ];
+        for patch in patches {
+            let owned = patch.to_patch();
+            assert_eq!(patch.kind(), owned.kind());
+            assert_eq!(
+                serde_json::to_string(&patch).unwrap(),
+                serde_json::to_string(&owned).unwrap()
+            );
+        }
+    }
 }
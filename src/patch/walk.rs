@@ -1,36 +1,133 @@
 use serde_json::Value;
 
-use crate::{errors::Error, Path};
-
-pub fn walk(value: &Value, path: Path) -> Result<&Value, Error> {
-    if let Some((head, tail)) = path.split_head() {
-        match value {
-            Value::Object(map) => map
-                .get(&head)
-                .ok_or(Error::PathDoesntExist)
-                .and_then(|value| walk(value, tail)),
-            Value::Array(vec) => {
-                let index = parse_array_index(vec, head)?;
-                walk(vec.get(index).ok_or(Error::PathDoesntExist)?, tail)
-            }
-            _ => Err(Error::PathDoesntExist),
+use crate::{
+    errors::{Error, PathErrorKind},
+    patch::apply::ApplyOptions,
+    path::{Pointer, Segment, Token},
+};
+
+// paths are walked one borrowed token at a time, so walking never allocates unless a token has to be unescaped
+
+pub fn walk<'a>(
+    value: &'a Value,
+    path: &impl Pointer,
+    options: &ApplyOptions,
+) -> Result<&'a Value, Error> {
+    path.segments()
+        .segments()
+        .enumerate()
+        .try_fold(value, |value, (depth, segment)| {
+            child(value, &segment, options).map_err(|kind| path_error(path, depth, kind))
+        })
+}
+
+fn child<'a>(
+    value: &'a Value,
+    segment: &Segment<'_>,
+    options: &ApplyOptions,
+) -> Result<&'a Value, PathErrorKind> {
+    match value {
+        Value::Object(map) => map
+            .get(segment.token.as_ref())
+            .ok_or(PathErrorKind::KeyNotFound),
+        Value::Array(vec) => {
+            let index = array_index(vec, segment.classify(), false, options)?;
+            vec.get(index).ok_or(PathErrorKind::IndexOutOfBounds {
+                index,
+                len: vec.len(),
+            })
+        }
+        _ => Err(PathErrorKind::NotAContainer),
+    }
+}
+
+pub fn walk_mut<'a>(
+    mut value: &'a mut Value,
+    path: &impl Pointer,
+    options: &ApplyOptions,
+) -> Result<&'a mut Value, Error> {
+    for (depth, segment) in path.segments().segments().enumerate() {
+        value =
+            child_mut(value, &segment, options).map_err(|kind| path_error(path, depth, kind))?;
+    }
+    Ok(value)
+}
+
+/// Walk to the parent of the location referred to by `path`, returning it along with the last segment of `path`
+///
+/// Returns `None` if `path` refers to the root, which has no parent
+pub fn walk_to_parent_mut<'a, 'p, P: Pointer>(
+    mut value: &'a mut Value,
+    path: &'p P,
+    options: &ApplyOptions,
+) -> Result<Option<(&'a mut Value, Segment<'p>)>, Error> {
+    let mut segments = path.segments().segments().enumerate().peekable();
+    while let Some((depth, segment)) = segments.next() {
+        if segments.peek().is_none() {
+            return Ok(Some((value, segment)));
         }
-    } else {
-        Ok(value)
+        value =
+            child_mut(value, &segment, options).map_err(|kind| path_error(path, depth, kind))?;
     }
+    Ok(None)
 }
 
-pub fn parse_array_index<T>(vec: &[T], s: impl AsRef<str>) -> Result<usize, Error> {
-    s.as_ref().parse::<usize>().or_else(|_| {
-        if s.as_ref() == "-" {
-            Ok(vec.len())
-        } else {
-            Err(Error::InvalidPath(format!(
-                "Expected array index, got '{}'",
-                s.as_ref(),
-            )))
+fn child_mut<'a>(
+    value: &'a mut Value,
+    segment: &Segment<'_>,
+    options: &ApplyOptions,
+) -> Result<&'a mut Value, PathErrorKind> {
+    match value {
+        Value::Object(map) => map
+            .get_mut(segment.token.as_ref())
+            .ok_or(PathErrorKind::KeyNotFound),
+        Value::Array(vec) => {
+            let index = array_index(vec, segment.classify(), false, options)?;
+            let len = vec.len();
+            vec.get_mut(index)
+                .ok_or(PathErrorKind::IndexOutOfBounds { index, len })
         }
-    })
+        _ => Err(PathErrorKind::NotAContainer),
+    }
+}
+
+/// Build the error for `path` failing to resolve at the token with index `depth`
+pub fn path_error(path: &impl Pointer, depth: usize, kind: PathErrorKind) -> Error {
+    let path = path.to_path();
+    Error::PathDoesntExist {
+        prefix: path.prefix(depth + 1),
+        path,
+        kind,
+    }
+}
+
+/// Build the error for the last token of `path` failing to resolve, such as when removing a missing value
+pub fn last_token_error(path: &impl Pointer, kind: PathErrorKind) -> Error {
+    let path = path.to_path();
+    Error::PathDoesntExist {
+        prefix: path.clone(),
+        path,
+        kind,
+    }
+}
+
+/// Resolve `token` as an index into `vec`, where `-` refers to the (nonexistent) element after the end
+///
+/// `-` is only allowed if `allow_end`, since it can be added to, but not read or removed. In strict mode, the token
+/// must be a [Token::Index], while lenient mode also accepts other numbers, such as `01` or `+1`
+pub fn array_index<T>(
+    vec: &[T],
+    token: Token<'_>,
+    allow_end: bool,
+    options: &ApplyOptions,
+) -> Result<usize, PathErrorKind> {
+    match token {
+        Token::Index(index) => Ok(index),
+        Token::End if allow_end => Ok(vec.len()),
+        Token::End => Err(PathErrorKind::EndOfArray),
+        Token::Key(key) if !options.strict => key.parse().map_err(|_| PathErrorKind::InvalidIndex),
+        Token::Key(_) => Err(PathErrorKind::InvalidIndex),
+    }
 }
 
 #[cfg(test)]
@@ -38,6 +135,7 @@ mod test {
     use serde_json::json;
 
     use super::*;
+    use crate::{Path, PathRef};
 
     fn default_json() -> Value {
         json!({
@@ -56,47 +154,174 @@ mod test {
     #[test]
     fn should_walk_json() {
         assert_eq!(
-            walk(&default_json(), Path::new("/a")).unwrap(),
+            walk(&default_json(), &Path::new("/a"), &ApplyOptions::default()).unwrap(),
             &json!("abc")
         );
-        assert_eq!(walk(&default_json(), Path::new("/b")).unwrap(), &json!(123));
         assert_eq!(
-            walk(&default_json(), Path::new("/c")).unwrap(),
+            walk(&default_json(), &Path::new("/b"), &ApplyOptions::default()).unwrap(),
+            &json!(123)
+        );
+        assert_eq!(
+            walk(&default_json(), &Path::new("/c"), &ApplyOptions::default()).unwrap(),
             &json!(true)
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/d")).unwrap(),
+            walk(&default_json(), &Path::new("/d"), &ApplyOptions::default()).unwrap(),
             &json!(null)
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/e")).unwrap(),
+            walk(&default_json(), &Path::new("/e"), &ApplyOptions::default()).unwrap(),
             &json!([1, 2, 3])
         );
-        assert_eq!(walk(&default_json(), Path::new("/e/0")).unwrap(), &json!(1));
-        assert_eq!(walk(&default_json(), Path::new("/e/2")).unwrap(), &json!(3));
         assert_eq!(
-            walk(&default_json(), Path::new("/f")).unwrap(),
+            walk(
+                &default_json(),
+                &Path::new("/e/0"),
+                &ApplyOptions::default()
+            )
+            .unwrap(),
+            &json!(1)
+        );
+        assert_eq!(
+            walk(
+                &default_json(),
+                &Path::new("/e/2"),
+                &ApplyOptions::default()
+            )
+            .unwrap(),
+            &json!(3)
+        );
+        assert_eq!(
+            walk(&default_json(), &Path::new("/f"), &ApplyOptions::default()).unwrap(),
             &json!({"a": "abc", "b": 123,})
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/f/a")).unwrap(),
+            walk(
+                &default_json(),
+                &Path::new("/f/a"),
+                &ApplyOptions::default()
+            )
+            .unwrap(),
             &json!("abc")
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/f/b")).unwrap(),
+            walk(
+                &default_json(),
+                &Path::new("/f/b"),
+                &ApplyOptions::default()
+            )
+            .unwrap(),
             &json!(123)
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/x/z")),
-            Err(Error::PathDoesntExist)
+            walk(
+                &default_json(),
+                &Path::new("/x/z"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/x/z"),
+                prefix: Path::new("/x"),
+                kind: PathErrorKind::KeyNotFound,
+            })
+        );
+        assert_eq!(
+            walk(
+                &default_json(),
+                &Path::new("/e/-"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/e/-"),
+                prefix: Path::new("/e/-"),
+                kind: PathErrorKind::EndOfArray,
+            })
         );
         assert_eq!(
-            walk(&default_json(), Path::new("/e/-")),
-            Err(Error::PathDoesntExist)
+            walk(
+                &default_json(),
+                &Path::new("/e/01"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/e/01"),
+                prefix: Path::new("/e/01"),
+                kind: PathErrorKind::InvalidIndex,
+            })
         );
         assert_eq!(
-            walk(&default_json(), Path::root()).unwrap(),
+            walk(
+                &default_json(),
+                &Path::new("/a/b/c"),
+                &ApplyOptions::default()
+            ),
+            Err(Error::PathDoesntExist {
+                path: Path::new("/a/b/c"),
+                prefix: Path::new("/a/b"),
+                kind: PathErrorKind::NotAContainer,
+            })
+        );
+        assert_eq!(
+            walk(&default_json(), &Path::root(), &ApplyOptions::default()).unwrap(),
             &default_json()
         )
     }
+
+    #[test]
+    fn path_refs_should_walk_like_paths() {
+        let paths = vec!["", "/a", "/e/2", "/f/b", "/x/z", "/e/-", "/e/01", "/a/b/c"];
+        for s in paths {
+            let options = ApplyOptions::default();
+            assert_eq!(
+                walk(&default_json(), &PathRef::new(s), &options),
+                walk(&default_json(), &Path::new(s), &options)
+            );
+        }
+
+        let mut json = json!({"a/b": {"~": [1]}});
+        let path = PathRef::new("/a~1b/~0");
+        let (parent, segment) = walk_to_parent_mut(&mut json, &path, &ApplyOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(segment.token, "~");
+        assert_eq!(parent, &json!({"~": [1]}));
+    }
+
+    #[test]
+    fn should_resolve_array_indices() {
+        let strict = ApplyOptions::default();
+        let lenient = ApplyOptions::default().strict(false);
+        let vec = [1, 2, 3];
+
+        assert_eq!(array_index(&vec, Token::parse("0"), false, &strict), Ok(0));
+        assert_eq!(
+            array_index(&vec, Token::parse("10"), false, &strict),
+            Ok(10)
+        );
+        assert_eq!(array_index(&vec, Token::parse("-"), true, &strict), Ok(3));
+        assert_eq!(
+            array_index(&vec, Token::parse("-"), false, &strict),
+            Err(PathErrorKind::EndOfArray)
+        );
+        for invalid in &["01", "00", "+1", "-1", "1e0", "", " 1", "a"] {
+            assert_eq!(
+                array_index(&vec, Token::parse(invalid), true, &strict),
+                Err(PathErrorKind::InvalidIndex)
+            );
+        }
+
+        assert_eq!(
+            array_index(&vec, Token::parse("01"), false, &lenient),
+            Ok(1)
+        );
+        assert_eq!(
+            array_index(&vec, Token::parse("+1"), false, &lenient),
+            Ok(1)
+        );
+        assert_eq!(
+            array_index(&vec, Token::parse("-"), false, &lenient),
+            Err(PathErrorKind::EndOfArray)
+        );
+        assert!(array_index(&vec, Token::parse("1e0"), false, &lenient).is_err());
+    }
 }
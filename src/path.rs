@@ -1,11 +1,60 @@
-use core::panic;
+use std::{
+    borrow::Cow, convert::TryFrom, fmt::Display, iter::FromIterator, slice, str::FromStr,
+    str::Split,
+};
 
 use serde::{de::Visitor, Deserialize, Serialize};
 
+use crate::errors::Error;
+
 /// A reference to location in a JSON document, as defined in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// A path is a list of unescaped tokens. It is parsed from, and displayed as, its escaped form:
+/// ```rust
+/// # use jatch::Path;
+/// let path: Path = vec!["foo", "a/b"].into_iter().collect();
+/// assert_eq!(path.to_string(), "/foo/a~1b");
+/// assert_eq!(path.iter().collect::<Vec<_>>(), vec!["foo", "a/b"]);
+/// assert_eq!("/foo/a~1b".parse::<Path>().unwrap(), path);
+/// ```
+/// Paths are ordered token by token, so a path sorts directly before its children
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Path {
-    parts: Vec<String>,
+    parts: Vec<Part>,
+}
+
+/// An unescaped token of a [Path], classified once when it is added so that walking the path doesn't classify it again
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Part {
+    token: String,
+    kind: TokenKind,
+}
+
+impl Part {
+    fn new(token: String) -> Self {
+        let kind = TokenKind::of(&token);
+        Self { token, kind }
+    }
+
+    fn as_str(&self) -> &str {
+        &self.token
+    }
+
+    fn token(&self) -> Token<'_> {
+        self.kind.with(&self.token)
+    }
+}
+
+impl std::fmt::Debug for Part {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.token, f)
+    }
+}
+
+impl PartialEq<&str> for Part {
+    fn eq(&self, other: &&str) -> bool {
+        self.token == *other
+    }
 }
 
 impl Serialize for Path {
@@ -38,14 +87,7 @@ impl<'a> Visitor<'a> for MyVisitor {
     where
         E: serde::de::Error,
     {
-        Ok(Path::new(v))
-    }
-
-    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
-    where
-        E: serde::de::Error,
-    {
-        Ok(Path::new(v))
+        Path::parse(v).map_err(E::custom)
     }
 }
 
@@ -54,31 +96,87 @@ const SLASH_ESCAPE: &str = "~1";
 
 impl Path {
     /// Create a new [Path] from an escaped string, as defined in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not a valid JSON Pointer. Use [Path::parse] for untrusted input
     pub fn new(s: impl AsRef<str>) -> Self {
-        if s.as_ref() == "" {
-            return Self::root();
-        };
+        match Self::parse(s) {
+            Ok(path) => path,
+            Err(e) => panic!("{}", e),
+        }
+    }
 
-        if !s.as_ref().starts_with('/') {
-            panic!("invalid json path: {}", s.as_ref())
+    /// Parse a [Path] from an escaped string, as defined in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
+    ///
+    /// Returns [Error::InvalidPath] if the string doesn't start with `/`, or contains a `~` that isn't followed by `0` or `1`
+    /// ```rust
+    /// # use jatch::{Error, Path};
+    /// assert_eq!(Path::parse("/foo/bar").unwrap(), Path::new("/foo/bar"));
+    /// assert!(matches!(Path::parse("foo"), Err(Error::InvalidPath { position: 0, .. })));
+    /// assert!(matches!(Path::parse("/foo~2"), Err(Error::InvalidPath { position: 4, .. })));
+    /// ```
+    pub fn parse(s: impl AsRef<str>) -> Result<Self, Error> {
+        let s = s.as_ref();
+        if s.is_empty() {
+            return Ok(Self::root());
         }
 
-        Self {
-            parts: s.as_ref().split('/').skip(1).map(Self::escape).collect(),
+        if !s.starts_with('/') {
+            return Err(Error::InvalidPath {
+                path: s.to_string(),
+                position: 0,
+            });
+        }
+
+        let mut parts = vec![];
+        // byte offset of the current token, used for error reporting
+        let mut offset = 1;
+        for token in s[1..].split('/') {
+            let part = Self::unescape(token).map_err(|position| Error::InvalidPath {
+                path: s.to_string(),
+                position: offset + position,
+            })?;
+            parts.push(Part::new(part));
+            offset += token.len() + 1;
         }
+
+        Ok(Self { parts })
     }
 
     /// Append a path to this path
-    /// A leading slash is added to the path
+    /// A leading slash is added to the path, which is then unescaped and split on `/`, so `s` may contain several tokens.
+    /// Use [Path::push] or [Path::child] to append a single token that may contain `/` or `~`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` contains an invalid escape sequence
     pub fn join(mut self, s: impl AsRef<str>) -> Self {
         let other = Path::new(format!("/{}", s.as_ref()));
         self.parts.extend(other.parts);
         self
     }
 
-    fn escape(s: impl AsRef<str>) -> String {
-        let s = s.as_ref().replace(SLASH_ESCAPE, "/");
-        s.replace(TILDE_ESCAPE, "~")
+    /// Unescape a single reference token, returning the offset of the offending `~` if the escape is invalid
+    fn unescape(token: &str) -> Result<String, usize> {
+        if !token.contains('~') {
+            return Ok(token.to_string());
+        }
+
+        let mut result = String::with_capacity(token.len());
+        let mut chars = token.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '~' {
+                match chars.next() {
+                    Some((_, '0')) => result.push('~'),
+                    Some((_, '1')) => result.push('/'),
+                    _ => return Err(i),
+                }
+            } else {
+                result.push(c);
+            }
+        }
+        Ok(result)
     }
 
     /// Create a [Path] pointing to the root of the document
@@ -86,24 +184,131 @@ impl Path {
         Self { parts: vec![] }
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
+    /// Whether this path points to the root of the document
+    pub fn is_empty(&self) -> bool {
         self.parts.is_empty()
     }
 
-    pub(crate) fn len(&self) -> usize {
+    /// The number of tokens in this path
+    pub fn len(&self) -> usize {
         self.parts.len()
     }
 
-    pub(crate) fn split_head(mut self) -> Option<(String, Self)> {
-        match &self.len() {
-            0 => None,
-            _ => {
-                let head = self.parts.remove(0);
-                Some((head, Self { parts: self.parts }))
-            }
+    /// The unescaped tokens of this path, in order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.parts.iter().map(Part::as_str)
+    }
+
+    /// The tokens of this path, classified by whether they can index into an array
+    /// ```rust
+    /// # use jatch::{Path, Token};
+    /// let path = Path::new("/foo/0/-/01");
+    /// let tokens = path.tokens().collect::<Vec<_>>();
+    /// assert_eq!(tokens, vec![Token::Key("foo"), Token::Index(0), Token::End, Token::Key("01")]);
+    /// ```
+    pub fn tokens(&self) -> impl DoubleEndedIterator<Item = Token<'_>> + ExactSizeIterator {
+        self.parts.iter().map(Part::token)
+    }
+
+    /// The last token of this path, or `None` if this is the root
+    /// ```rust
+    /// # use jatch::Path;
+    /// assert_eq!(Path::new("/foo/a~1b").last(), Some("a/b"));
+    /// assert_eq!(Path::root().last(), None);
+    /// ```
+    pub fn last(&self) -> Option<&str> {
+        self.parts.last().map(Part::as_str)
+    }
+
+    /// The path to the parent of the value at this path, or `None` if this is the root
+    /// ```rust
+    /// # use jatch::Path;
+    /// assert_eq!(Path::new("/foo/bar").parent(), Some(Path::new("/foo")));
+    /// assert_eq!(Path::new("/foo").parent(), Some(Path::root()));
+    /// assert_eq!(Path::root().parent(), None);
+    /// ```
+    pub fn parent(&self) -> Option<Path> {
+        let (_, parent) = self.parts.split_last()?;
+        Some(Self {
+            parts: parent.to_vec(),
+        })
+    }
+
+    /// Whether `prefix` is a prefix of this path, where a path is considered a prefix of itself
+    ///
+    /// Tokens are compared as a whole, so `/ab` doesn't start with `/a`
+    /// ```rust
+    /// # use jatch::Path;
+    /// assert!(Path::new("/a/b").starts_with(&Path::new("/a")));
+    /// assert!(Path::new("/a").starts_with(&Path::new("/a")));
+    /// assert!(!Path::new("/ab").starts_with(&Path::new("/a")));
+    /// ```
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        self.parts.starts_with(&prefix.parts)
+    }
+
+    /// The path made up of the first `len` tokens of this path
+    pub(crate) fn prefix(&self, len: usize) -> Self {
+        Self {
+            parts: self.parts[..len.min(self.len())].to_vec(),
         }
     }
 
+    /// Append a single token to this path, which is taken as-is, without unescaping
+    ///
+    /// Unlike [Path::join], any `/` or `~` in `token` is part of the token, so this can refer to any object key
+    /// ```rust
+    /// # use jatch::Path;
+    /// let mut path = Path::new("/foo");
+    /// path.push("a/b~c");
+    /// assert_eq!(path, Path::new("/foo/a~1b~0c"));
+    /// ```
+    pub fn push(&mut self, token: impl Into<String>) {
+        self.parts.push(Part::new(token.into()));
+    }
+
+    /// The path to the child `token` of the value at this path, where `token` is taken as-is, like [Path::push]
+    /// ```rust
+    /// # use jatch::Path;
+    /// let parent = Path::new("/foo");
+    /// assert_eq!(parent.child("~1"), Path::new("/foo/~01"));
+    /// assert_eq!(parent.child(""), Path::new("/foo/"));
+    /// ```
+    pub fn child(&self, token: impl Into<String>) -> Self {
+        let mut child = self.clone();
+        child.push(token);
+        child
+    }
+
+    /// The rest of this path after `prefix`, or `None` if `prefix` isn't a prefix of this path
+    /// ```rust
+    /// # use jatch::Path;
+    /// assert_eq!(Path::new("/a/b/c").strip_prefix(&Path::new("/a")), Some(Path::new("/b/c")));
+    /// assert_eq!(Path::new("/a").strip_prefix(&Path::new("/a")), Some(Path::root()));
+    /// assert_eq!(Path::new("/a").strip_prefix(&Path::new("/b")), None);
+    /// ```
+    pub fn strip_prefix(&self, prefix: &Path) -> Option<Path> {
+        self.parts
+            .strip_prefix(prefix.parts.as_slice())
+            .map(|parts| Self {
+                parts: parts.to_vec(),
+            })
+    }
+
+    /// Remove the last token from this path, returning it unescaped, or `None` if this is the root
+    pub fn pop(&mut self) -> Option<String> {
+        self.parts.pop().map(|part| part.token)
+    }
+
+    /// This path with its last token replaced by `token`, which is taken as-is, without unescaping
+    pub(crate) fn with_last(&self, token: String) -> Self {
+        let mut parts = self.parts.clone();
+        if let Some(last) = parts.last_mut() {
+            *last = Part::new(token);
+        }
+        Self { parts }
+    }
+
     pub(crate) fn to_escaped(&self) -> String {
         if self.is_empty() {
             String::from("")
@@ -111,8 +316,8 @@ impl Path {
             let s = self
                 .parts
                 .iter()
-                .map(|s| {
-                    let s = s.replace('~', TILDE_ESCAPE);
+                .map(|part| {
+                    let s = part.token.replace('~', TILDE_ESCAPE);
                     s.replace('/', SLASH_ESCAPE)
                 })
                 .collect::<Vec<_>>()
@@ -122,27 +327,395 @@ impl Path {
     }
 }
 
-impl<T> From<T> for Path
-where
-    T: AsRef<str>,
-{
-    fn from(t: T) -> Self {
-        let s = t.as_ref();
-        Path::new(s)
+/// Displays the escaped form of the path, which [Path::parse] accepts
+impl Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_escaped())
+    }
+}
+
+/// Collects unescaped tokens into a path, as if each was passed to [Path::push]
+impl<T: Into<String>> FromIterator<T> for Path {
+    fn from_iter<I: IntoIterator<Item = T>>(tokens: I) -> Self {
+        Self {
+            parts: tokens
+                .into_iter()
+                .map(|token| Part::new(token.into()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Into<String>> Extend<T> for Path {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, tokens: I) {
+        self.parts
+            .extend(tokens.into_iter().map(|token| Part::new(token.into())));
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Path::parse(s)
+    }
+}
+
+impl TryFrom<&str> for Path {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Path::parse(s)
+    }
+}
+
+impl TryFrom<String> for Path {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Path::parse(s)
+    }
+}
+
+/// A single unescaped token of a path, classified by how it can index into an array
+///
+/// Every token can be used as an object key, including [Token::Index] and [Token::End]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Token<'a> {
+    /// Any other token, such as `foo`, which is only an object key. In lenient mode, a key that parses as a number,
+    /// such as `01` or `+1`, can also refer to an array element
+    Key(&'a str),
+    /// A token matching the array index grammar of [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901#section-4),
+    /// which is `0` or a number without a sign or leading zeros
+    Index(usize),
+    /// `-`, which refers to the nonexistent element after the end of an array, so it can only be added to
+    End,
+}
+
+impl<'a> Token<'a> {
+    /// Classify an unescaped token
+    ///
+    /// An index that is too large for a `usize` can't refer to any element, so it is a [Token::Key]
+    /// ```rust
+    /// # use jatch::Token;
+    /// assert_eq!(Token::parse("10"), Token::Index(10));
+    /// assert_eq!(Token::parse("-"), Token::End);
+    /// assert_eq!(Token::parse("+1"), Token::Key("+1"));
+    /// ```
+    pub fn parse(token: &'a str) -> Self {
+        TokenKind::of(token).with(token)
+    }
+}
+
+/// The classification of a [Token], without the token itself, so it can be stored alongside an owned token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum TokenKind {
+    Key,
+    Index(usize),
+    End,
+}
+
+impl TokenKind {
+    fn of(token: &str) -> Self {
+        if token == "-" {
+            return TokenKind::End;
+        }
+        let is_index = token == "0"
+            || (!token.is_empty()
+                && !token.starts_with('0')
+                && token.bytes().all(|b| b.is_ascii_digit()));
+        match token.parse() {
+            Ok(index) if is_index => TokenKind::Index(index),
+            _ => TokenKind::Key,
+        }
+    }
+
+    fn with(self, token: &str) -> Token<'_> {
+        match self {
+            TokenKind::Key => Token::Key(token),
+            TokenKind::Index(index) => Token::Index(index),
+            TokenKind::End => Token::End,
+        }
+    }
+}
+
+/// A JSON Pointer borrowed from its escaped form, as defined in [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
+///
+/// Unlike [Path], parsing a [PathRef] doesn't allocate. Tokens are split off as they are iterated, and only a token that
+/// contains an escaped `~` or `/` is copied to unescape it:
+/// ```rust
+/// # use jatch::{Path, PathRef};
+/// let path = PathRef::parse("/foo/a~1b").unwrap();
+/// assert_eq!(path.tokens().collect::<Vec<_>>(), vec!["foo", "a/b"]);
+/// assert_eq!(path.to_path(), Path::new("/foo/a~1b"));
+/// ```
+/// A [PathRef] is deserialized by borrowing from the input, so the input must be a string without JSON escape sequences
+/// that the deserializer can lend out, such as with [serde_json::from_str]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PathRef<'a> {
+    escaped: &'a str,
+}
+
+impl<'a> PathRef<'a> {
+    /// Create a new [PathRef] from an escaped string
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not a valid JSON Pointer. Use [PathRef::parse] for untrusted input
+    pub fn new(s: &'a str) -> Self {
+        match Self::parse(s) {
+            Ok(path) => path,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Parse a [PathRef] from an escaped string, with the same rules and errors as [Path::parse]
+    /// ```rust
+    /// # use jatch::{Error, PathRef};
+    /// assert_eq!(PathRef::parse("/foo/bar").unwrap().len(), 2);
+    /// assert!(matches!(PathRef::parse("foo"), Err(Error::InvalidPath { position: 0, .. })));
+    /// assert!(matches!(PathRef::parse("/foo~2"), Err(Error::InvalidPath { position: 4, .. })));
+    /// ```
+    pub fn parse(s: &'a str) -> Result<Self, Error> {
+        let invalid = |position| Error::InvalidPath {
+            path: s.to_string(),
+            position,
+        };
+        if !s.is_empty() && !s.starts_with('/') {
+            return Err(invalid(0));
+        }
+
+        let bytes = s.as_bytes();
+        for (i, &b) in bytes.iter().enumerate() {
+            if b == b'~' && !matches!(bytes.get(i + 1), Some(b'0') | Some(b'1')) {
+                return Err(invalid(i));
+            }
+        }
+        Ok(Self { escaped: s })
+    }
+
+    /// Create a [PathRef] pointing to the root of the document
+    pub fn root() -> Self {
+        Self { escaped: "" }
+    }
+
+    /// Whether this path points to the root of the document
+    pub fn is_empty(&self) -> bool {
+        self.escaped.is_empty()
+    }
+
+    /// The number of tokens in this path
+    pub fn len(&self) -> usize {
+        self.escaped.matches('/').count()
+    }
+
+    /// The escaped form of this path, as it was parsed
+    pub fn as_str(&self) -> &'a str {
+        self.escaped
+    }
+
+    /// The unescaped tokens of this path, in order
+    pub fn tokens(&self) -> Tokens<'a> {
+        Tokens {
+            inner: match self.escaped.get(1..) {
+                Some(rest) => TokensInner::Escaped(rest.split('/')),
+                None => TokensInner::Unescaped([].iter()),
+            },
+        }
+    }
+
+    /// Copy this path into an owned [Path]
+    pub fn to_path(&self) -> Path {
+        self.tokens().collect()
+    }
+}
+
+/// Displays the escaped form of the path, exactly as it was parsed
+impl Display for PathRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.escaped)
+    }
+}
+
+impl From<PathRef<'_>> for Path {
+    fn from(path: PathRef<'_>) -> Self {
+        path.to_path()
+    }
+}
+
+impl<'a> TryFrom<&'a str> for PathRef<'a> {
+    type Error = Error;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        PathRef::parse(s)
+    }
+}
+
+impl Serialize for PathRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.escaped)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for PathRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(BorrowedVisitor)
+    }
+}
+
+struct BorrowedVisitor;
+impl<'de> Visitor<'de> for BorrowedVisitor {
+    type Value = PathRef<'de>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a borrowed string representing a json pointer")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        PathRef::parse(v).map_err(E::custom)
+    }
+}
+
+/// An iterator over the unescaped tokens of a path, returned by [PathRef::tokens]
+///
+/// Tokens are borrowed, unless they had to be unescaped
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    inner: TokensInner<'a>,
+}
+
+#[derive(Debug, Clone)]
+enum TokensInner<'a> {
+    /// The rest of an escaped path, after its leading `/`
+    Escaped(Split<'a, char>),
+    /// The tokens of a [Path], which are already unescaped
+    Unescaped(slice::Iter<'a, Part>),
+}
+
+impl<'a> Tokens<'a> {
+    /// The remaining tokens, along with their classification, which a [Path] stores rather than working it out again
+    pub(crate) fn segments(self) -> impl Iterator<Item = Segment<'a>> {
+        let mut tokens = self;
+        std::iter::from_fn(move || tokens.next_segment())
+    }
+
+    fn next_segment(&mut self) -> Option<Segment<'a>> {
+        match &mut self.inner {
+            TokensInner::Escaped(split) => {
+                let token = split.next()?;
+                let token = if token.contains('~') {
+                    // the path was validated when it was parsed
+                    Cow::Owned(Path::unescape(token).ok()?)
+                } else {
+                    Cow::Borrowed(token)
+                };
+                let kind = TokenKind::of(&token);
+                Some(Segment { token, kind })
+            }
+            TokensInner::Unescaped(parts) => parts.next().map(|part| Segment {
+                token: Cow::Borrowed(part.as_str()),
+                kind: part.kind,
+            }),
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_segment().map(|segment| segment.token)
+    }
+}
+
+/// An unescaped token of a [Pointer], along with its classification
+#[derive(Debug)]
+pub(crate) struct Segment<'a> {
+    pub(crate) token: Cow<'a, str>,
+    kind: TokenKind,
+}
+
+impl Segment<'_> {
+    /// The token, classified by how it can index into an array
+    pub(crate) fn classify(&self) -> Token<'_> {
+        self.kind.with(&self.token)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Path {}
+    impl Sealed for super::PathRef<'_> {}
+}
+
+/// A path that can be resolved against a document, implemented by both [Path] and [PathRef]
+///
+/// Functions such as [get](crate::get) accept either, so a [PathRef] can be resolved without allocating its tokens.
+/// This trait is sealed, and can't be implemented outside of this crate
+/// ```rust
+/// # use jatch::{get, Path, PathRef};
+/// # use serde_json::json;
+/// let root = json!({"a": {"b": 1}});
+/// let path = Path::new("/a/b");
+/// let path_ref = PathRef::parse("/a/b").unwrap();
+/// assert_eq!(get(&root, &path), get(&root, &path_ref));
+/// ```
+pub trait Pointer: sealed::Sealed {
+    /// The unescaped tokens of this path
+    fn segments(&self) -> Tokens<'_>;
+
+    /// Copy this path into an owned [Path]
+    fn to_path(&self) -> Path;
+}
+
+impl Pointer for Path {
+    fn segments(&self) -> Tokens<'_> {
+        Tokens {
+            inner: TokensInner::Unescaped(self.parts.iter()),
+        }
+    }
+
+    fn to_path(&self) -> Path {
+        self.clone()
+    }
+}
+
+impl Pointer for PathRef<'_> {
+    fn segments(&self) -> Tokens<'_> {
+        PathRef::tokens(self)
+    }
+
+    fn to_path(&self) -> Path {
+        PathRef::to_path(self)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::convert::TryInto;
+
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
     fn can_create_from_strings() {
-        Path::from("/foo");
-        let _: Path = "/foo".into();
+        let _ = Path::try_from("/foo").unwrap();
+        let _: Path = "/foo".parse().unwrap();
+        let _: Path = "/foo".try_into().unwrap();
 
         let s = "/foo".to_string();
-        let _: Path = s.into();
+        let _: Path = s.try_into().unwrap();
     }
 
     #[test]
@@ -151,7 +724,7 @@ mod test {
         assert_eq!(Path::new("/foo/bar").parts, vec!["foo", "bar"]);
         assert_eq!(Path::new("/").parts, vec![""]);
         assert_eq!(Path::new("//foo").parts, vec!["", "foo"]);
-        assert_eq!(Path::new("").parts, Vec::<String>::new());
+        assert_eq!(Path::new("").parts, Vec::<&str>::new());
     }
 
     #[test]
@@ -161,6 +734,36 @@ mod test {
         assert_eq!(Path::new("/~01").parts, vec!["~1"]);
     }
 
+    #[test]
+    fn should_reject_invalid_paths() {
+        let invalid = |path: &str, position: usize| Error::InvalidPath {
+            path: path.to_string(),
+            position,
+        };
+        assert_eq!(Path::parse("foo"), Err(invalid("foo", 0)));
+        assert_eq!(Path::parse("~0"), Err(invalid("~0", 0)));
+        assert_eq!(Path::parse("/~2"), Err(invalid("/~2", 1)));
+        assert_eq!(Path::parse("/foo~"), Err(invalid("/foo~", 4)));
+        assert_eq!(Path::parse("/foo/b~ar"), Err(invalid("/foo/b~ar", 6)));
+        assert_eq!(Path::parse("/~0/~1/~"), Err(invalid("/~0/~1/~", 7)));
+        assert_eq!(Path::parse("/é/~x"), Err(invalid("/é/~x", 4)));
+    }
+
+    #[test]
+    fn should_fail_to_deserialize_invalid_paths() {
+        use serde_json::from_str;
+
+        assert_eq!(from_str::<Path>(r#""/foo""#).unwrap(), Path::new("/foo"));
+        assert!(from_str::<Path>(r#""foo""#).is_err());
+        assert!(from_str::<Path>(r#""/~2""#).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_should_panic_on_invalid_paths() {
+        Path::new("foo");
+    }
+
     #[test]
     fn root_should_equal_empty_string() {
         assert_eq!(Path::new(""), Path::root());
@@ -171,6 +774,184 @@ mod test {
         let paths = vec!["", "/", "/~0", "/~1", "/~01", "/hello/~0asdf~1/world"];
         for path in paths {
             assert_eq!(path, &Path::new(path).to_escaped());
+            assert_eq!(path, Path::new(path).to_string());
+        }
+    }
+
+    #[test]
+    fn tokens_should_round_trip() {
+        let tokens = vec!["", "-", "0", "~", "/", "~1", "a/b~c", "é"];
+        let path = tokens.iter().copied().collect::<Path>();
+        assert_eq!(path.iter().collect::<Vec<_>>(), tokens);
+        assert_eq!(path.to_string(), "//-/0/~0/~1/~01/a~1b~0c/é");
+        assert_eq!(path.to_string().parse::<Path>(), Ok(path.clone()));
+
+        let mut pushed = Path::root();
+        pushed.extend(tokens[..2].iter().copied());
+        for token in &tokens[2..] {
+            pushed.push(*token);
+        }
+        assert_eq!(pushed, path);
+        assert_eq!(pushed.len(), tokens.len());
+
+        for token in tokens.iter().rev() {
+            let parent = pushed.parent().unwrap();
+            assert_eq!(pushed.last(), Some(*token));
+            assert_eq!(
+                pushed.strip_prefix(&parent),
+                Some(Path::from_iter(vec![*token]))
+            );
+            assert_eq!(parent.child(*token), pushed);
+            assert_eq!(pushed.pop().as_deref(), Some(*token));
+            assert_eq!(pushed, parent);
+        }
+        assert!(pushed.is_empty());
+        assert_eq!(pushed.pop(), None);
+        assert_eq!(pushed, Path::default());
+    }
+
+    #[test]
+    fn paths_should_be_ordered_by_token() {
+        let mut paths = vec![
+            Path::new("/b"),
+            Path::new("/a~1b"),
+            Path::new("/a/b"),
+            Path::root(),
+            Path::new("/a"),
+        ];
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                Path::root(),
+                Path::new("/a"),
+                Path::new("/a/b"),
+                Path::new("/a~1b"),
+                Path::new("/b"),
+            ]
+        );
+
+        let set = paths
+            .iter()
+            .cloned()
+            .collect::<std::collections::HashSet<_>>();
+        assert!(set.contains(&Path::from_iter(vec!["a/b"])));
+        assert!(!set.contains(&Path::from_iter(vec!["a", "c"])));
+    }
+
+    #[test]
+    fn tokens_should_be_classified_by_the_index_grammar() {
+        let cases = vec![
+            ("0", Token::Index(0)),
+            ("10", Token::Index(10)),
+            ("-", Token::End),
+            ("", Token::Key("")),
+            ("01", Token::Key("01")),
+            ("00", Token::Key("00")),
+            ("-1", Token::Key("-1")),
+            ("+1", Token::Key("+1")),
+            ("1e0", Token::Key("1e0")),
+            (" 1", Token::Key(" 1")),
+            ("--", Token::Key("--")),
+            ("18446744073709551616", Token::Key("18446744073709551616")),
+        ];
+        for (token, expected) in cases {
+            assert_eq!(Token::parse(token), expected, "{}", token);
+        }
+    }
+
+    #[test]
+    fn paths_should_keep_tokens_classified_as_they_change() {
+        let mut path: Path = vec!["a", "0"].into_iter().collect();
+        path.push("-");
+        path.extend(vec!["01"]);
+        let path = path.join("5").with_last("7".to_string());
+        assert_eq!(
+            path.tokens().collect::<Vec<_>>(),
+            path.iter().map(Token::parse).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            path.tokens().collect::<Vec<_>>(),
+            vec![
+                Token::Key("a"),
+                Token::Index(0),
+                Token::End,
+                Token::Key("01"),
+                Token::Index(7)
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", Path::new("/a/0")),
+            r#"Path { parts: ["a", "0"] }"#
+        );
+    }
+
+    #[test]
+    fn path_refs_should_parse_like_paths() {
+        let valid = vec![
+            "",
+            "/",
+            "//",
+            "/foo/bar",
+            "/~0/~1/~01",
+            "/a~1b~0c/é",
+            "/-/0",
+        ];
+        for s in valid {
+            let path = PathRef::parse(s).unwrap();
+            assert_eq!(path.to_path(), Path::new(s));
+            assert_eq!(path.len(), Path::new(s).len());
+            assert_eq!(path.is_empty(), s.is_empty());
+            assert_eq!(path.to_string(), s);
+        }
+
+        let invalid = vec![
+            "foo",
+            "~0",
+            "/~2",
+            "/foo~",
+            "/foo/b~ar",
+            "/~0/~1/~",
+            "/é/~x",
+        ];
+        for s in invalid {
+            assert_eq!(PathRef::parse(s), Err(Path::parse(s).unwrap_err()));
+        }
+        assert_eq!(PathRef::root().to_path(), Path::root());
+    }
+
+    #[test]
+    fn path_refs_should_only_allocate_escaped_tokens() {
+        let tokens = PathRef::new("/foo/a~1b/~0").tokens().collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["foo", "a/b", "~"]);
+        assert!(matches!(tokens[0], Cow::Borrowed("foo")));
+        assert!(matches!(tokens[1], Cow::Owned(_)));
+    }
+
+    #[test]
+    fn path_refs_should_borrow_when_deserialized() {
+        let json = r#"["/foo/a~1b",""]"#;
+        let paths = serde_json::from_str::<Vec<PathRef>>(json).unwrap();
+        assert_eq!(paths, vec![PathRef::new("/foo/a~1b"), PathRef::root()]);
+        assert_eq!(serde_json::to_string(&paths).unwrap(), json);
+
+        assert!(serde_json::from_str::<PathRef>(r#""foo""#).is_err());
+        // a string with JSON escapes can't be borrowed from the input
+        assert!(serde_json::from_str::<PathRef>(r#""/foo\n""#).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_tokens_round_trip(tokens in prop::collection::vec("[~/a0-]{0,3}|\\PC{0,3}", 0..4)) {
+            let path = tokens.iter().collect::<Path>();
+            prop_assert_eq!(path.to_string().parse::<Path>(), Ok(path.clone()));
+            prop_assert_eq!(path.iter().collect::<Vec<_>>(), tokens.iter().map(String::as_str).collect::<Vec<_>>());
+            prop_assert_eq!(path.parent().is_none(), tokens.is_empty());
+
+            let escaped = path.to_string();
+            let path_ref = PathRef::parse(&escaped).unwrap();
+            prop_assert_eq!(path_ref.tokens().collect::<Vec<_>>(), tokens);
+            prop_assert_eq!(path_ref.to_path(), path);
         }
     }
 }